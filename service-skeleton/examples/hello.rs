@@ -12,6 +12,7 @@ struct Config {
 
 fn main() {
 	service("Hello")
+		.version(env!("CARGO_PKG_VERSION"))
 		.counter::<Vec<(&str, String)>>("count", "Number of times we've said hello")
 		.run(|cfg| say_hello(cfg));
 }
@@ -0,0 +1,203 @@
+//! Overriding config values with command-line flags.
+//!
+//! Each env var that a config reads from gets a flag named after it, less the prefix, in
+//! `kebab-case`, so `<PREFIX>_DB_HOST` can be set with `--db-host`.  Setting a flag is exactly
+//! like setting the env var, except that it takes precedence over the environment.
+//!
+
+use heck::{AsKebabCase, AsShoutySnekCase};
+
+use std::collections::HashMap;
+
+use super::{EnvVar, EnvVarKind, Service};
+use crate::Error;
+
+/// The given `vars`, with the values for any flags for `CFG`'s fields in `args` added, along
+/// with the flag that each of the vars set by a flag was set by.
+///
+/// # Errors
+///
+/// As for [`apply_args`].
+///
+pub(crate) fn vars_with_args<CFG: Service>(
+	prefix: &str,
+	vars: impl Iterator<Item = (String, String)>,
+	args: impl Iterator<Item = String>,
+) -> Result<(HashMap<String, String>, HashMap<String, String>), Error> {
+	let mut vars: HashMap<String, String> = vars.collect();
+	let mut flags: HashMap<String, String> = HashMap::new();
+
+	if CFG::cli_flags() {
+		apply_args(
+			&CFG::fields(&env_prefix(prefix)),
+			prefix,
+			&mut vars,
+			&mut flags,
+			args,
+		)?;
+	}
+
+	Ok((vars, flags))
+}
+
+/// The prefix that the derived config will actually use for its env vars.
+fn env_prefix(prefix: &str) -> String {
+	AsShoutySnekCase(prefix).to_string()
+}
+
+/// The flag for setting the given var.
+fn flag_for(var: &EnvVar, prefix: &str) -> String {
+	let name = var
+		.name
+		.strip_prefix(&format!("{}_", env_prefix(prefix)))
+		.unwrap_or(&var.name);
+	format!("--{}", AsKebabCase(name))
+}
+
+/// Add the values given in `args` to `vars`, replacing any values already there, and note the
+/// flag that set each of them in `flags`.
+///
+/// Arguments which aren't flags for one of the `config_vars` are ignored, as they're presumably
+/// meant for the service itself.
+///
+/// # Errors
+///
+/// If a flag for a sensitive var is given, because anyone who can run `ps` would be able to see
+/// its value, or if a flag is missing its value.
+///
+fn apply_args(
+	config_vars: &[EnvVar],
+	prefix: &str,
+	vars: &mut HashMap<String, String>,
+	flags: &mut HashMap<String, String>,
+	args: impl Iterator<Item = String>,
+) -> Result<(), Error> {
+	let mut args = args.peekable();
+	let mut list_items: HashMap<&str, (String, Vec<String>)> = HashMap::new();
+
+	while let Some(arg) = args.next() {
+		let (flag, inline_value) = match arg.split_once('=') {
+			Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+			None => (arg, None),
+		};
+
+		let Some((var, target)) = config_vars.iter().find_map(|var| {
+			let var_flag = flag_for(var, prefix);
+			if flag == var_flag {
+				Some((var, var.name.clone()))
+			} else if var.from_file && flag == format!("{var_flag}-file") {
+				Some((var, format!("{}_FILE", var.name)))
+			} else {
+				None
+			}
+		}) else {
+			continue;
+		};
+
+		if var.kind == EnvVarKind::Map {
+			return Err(Error::config_flag(
+				&flag,
+				format!(
+					"maps can only be set in the environment, as {}_<KEY>",
+					var.name
+				),
+			));
+		}
+		if var.sensitive && target == var.name {
+			return Err(Error::config_flag(
+				&flag,
+				format!(
+					"{} is sensitive, and command-line arguments can be seen by anyone; set it in the environment instead",
+					var.name
+				),
+			));
+		}
+
+		let value = match inline_value {
+			Some(value) => value,
+			None if var.is_bool && args.peek().is_none_or(|next| next.starts_with("--")) => {
+				"true".to_string()
+			}
+			None => args
+				.next()
+				.ok_or_else(|| Error::config_flag(&flag, "no value given"))?,
+		};
+
+		if var.kind == EnvVarKind::List && target == var.name {
+			list_items
+				.entry(&var.name)
+				.or_insert_with(|| (flag, Vec::new()))
+				.1
+				.push(value);
+		} else {
+			vars.insert(target.clone(), value);
+			flags.insert(target, flag);
+		}
+	}
+
+	// A list given once is just like the env var, and can be separated as usual, but a list
+	// given more than once has one item per flag
+	for (name, (flag, items)) in list_items {
+		if let [value] = items.as_slice() {
+			vars.insert(name.to_string(), value.clone());
+			flags.insert(name.to_string(), flag);
+		} else {
+			vars.remove(name);
+			vars.retain(|var, _| {
+				!var.strip_prefix(&format!("{name}_"))
+					.is_some_and(|suffix| suffix.parse::<usize>().is_ok())
+			});
+			for (i, item) in items.into_iter().enumerate() {
+				vars.insert(format!("{name}_{i}"), item);
+				flags.insert(format!("{name}_{i}"), flag.clone());
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Describe the flags for all of `CFG`'s fields, for `--help`.
+pub(crate) fn help<CFG: Service>(prefix: &str) -> Vec<[String; 2]> {
+	CFG::fields(&env_prefix(prefix))
+		.iter()
+		.flat_map(|var| {
+			let flag = flag_for(var, prefix);
+			let mut about = var
+				.description
+				.split_whitespace()
+				.collect::<Vec<_>>()
+				.join(" ");
+			if let Some(default) = var.default_text() {
+				about = format!("{about} [default: {default}]");
+			}
+
+			let mut lines = Vec::new();
+			if var.kind == EnvVarKind::Map {
+				lines.push([
+					format!("{}_<KEY>", var.name),
+					format!("{about} (environment only)"),
+				]);
+			} else if var.sensitive {
+				lines.push([var.name.clone(), format!("{about} (environment only)")]);
+			} else if var.is_bool {
+				lines.push([flag.clone(), format!("{about} [env: {}]", var.name)]);
+			} else {
+				lines.push([
+					format!("{flag} <{}>", var.type_name),
+					format!("{about} [env: {}]", var.name),
+				]);
+			}
+			if var.from_file {
+				lines.push([
+					format!("{flag}-file <PATH>"),
+					format!(
+						"Read the value of {} from a file [env: {}_FILE]",
+						var.name, var.name
+					),
+				]);
+			}
+			lines
+		})
+		.collect()
+}
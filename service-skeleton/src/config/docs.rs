@@ -0,0 +1,339 @@
+//! Describing all the environment variables that a service takes, for the benefit of the
+//! people who have to run it.
+//!
+
+use heck::AsShoutySnekCase;
+
+use super::Service;
+
+/// The settings that the service skeleton itself reads, as `<PREFIX>_<NAME>`, type, and
+/// description.
+pub(crate) const SKELETON_VARS: &[(&str, &str, &str)] = &[
+	(
+		"CONFIG_FILE",
+		"PathBuf",
+		"A TOML, YAML, or JSON file to read config values from; values set in the environment take precedence.",
+	),
+	(
+		"LOG",
+		"String",
+		"Log filter directives, like `info` or `my_service=debug,hyper=warn`.",
+	),
+	(
+		"LOG_SINK",
+		"String",
+		"Where to send logs: `stderr`, `journald[:<socket>]`, or `syslog[:<socket>]`.",
+	),
+	(
+		"METRICS_SERVER_PORT",
+		"u16",
+		"The port to serve Prometheus metrics on; no metrics server is started if this isn't set.",
+	),
+];
+
+/// How a variable's value is given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EnvVarKind {
+	/// A single value.
+	Value,
+	/// A list of values, either separated in the variable itself, or one per `<VAR>_0`,
+	/// `<VAR>_1`, and so on.
+	List,
+	/// A map, with one `<VAR>_<KEY>` variable per entry.
+	Map,
+}
+
+/// Everything there is to know about one of the environment variables that a service takes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EnvVar {
+	/// The name of the variable, or, for maps, the prefix of the names of the variables, less
+	/// the trailing underscore.
+	pub name: String,
+	/// The config field that the variable is read into, as a path from the top of the config,
+	/// like `db.host` for the `host` field of a `nested` (or `flatten`ed) `db` field; `None` for
+	/// variables that aren't read into a field, like key files, and the skeleton's own settings.
+	pub field: Option<String>,
+	/// How the variable's value is given.
+	pub kind: EnvVarKind,
+	/// The Rust type of the value, less any wrapping `Option`.
+	pub type_name: &'static str,
+	/// The value used if the variable isn't set, if there is one.
+	pub default: Option<&'static str>,
+	/// The Rust expression that gives the value used if the variable isn't set, for fields with a
+	/// `#[config(default)]` rather than a `default_value`.
+	pub default_expr: Option<&'static str>,
+	/// The doc comment on the config field.
+	pub description: &'static str,
+	/// Whether the field is an `Option`, and so can be left unset.
+	pub optional: bool,
+	/// Whether the value is a secret, which gets removed from the environment once it is read.
+	pub sensitive: bool,
+	/// Whether the value is encrypted.
+	pub encrypted: bool,
+	/// Whether the value can instead be read from the file named in `<VAR>_FILE`.
+	pub from_file: bool,
+	/// The variable which names the file containing the key to decrypt the value, if it is
+	/// encrypted.
+	pub key_file: Option<String>,
+	/// Whether the value is a `bool`, whose command-line flag can be given without a value.
+	pub is_bool: bool,
+}
+
+impl EnvVar {
+	// Only for use by derived code, which can't construct a non_exhaustive struct itself
+	#[doc(hidden)]
+	#[must_use]
+	pub fn new(name: String, kind: EnvVarKind, type_name: &'static str) -> Self {
+		Self {
+			name,
+			field: None,
+			kind,
+			type_name,
+			default: None,
+			default_expr: None,
+			description: "",
+			optional: false,
+			sensitive: false,
+			encrypted: false,
+			from_file: false,
+			key_file: None,
+			is_bool: false,
+		}
+	}
+
+	/// Whether the service won't start unless the variable is set.
+	#[must_use]
+	pub fn is_required(&self) -> bool {
+		!self.optional && self.default_text().is_none() && self.kind != EnvVarKind::Map
+	}
+
+	/// The default, as either the value itself, or the expression that gives it.
+	pub(crate) fn default_text(&self) -> Option<&'static str> {
+		self.default.or(self.default_expr)
+	}
+
+	/// The name of the variable, as the operator would need to write it; for maps, that's
+	/// `<VAR>_<KEY>`.
+	#[must_use]
+	pub fn display_name(&self) -> String {
+		if self.kind == EnvVarKind::Map {
+			format!("{}_<KEY>", self.name)
+		} else {
+			self.name.clone()
+		}
+	}
+
+	/// The first paragraph of the description, all on one line.
+	fn summary(&self) -> String {
+		self.description
+			.split("\n\n")
+			.next()
+			.unwrap_or_default()
+			.split_whitespace()
+			.collect::<Vec<_>>()
+			.join(" ")
+	}
+
+	/// Short remarks about the variable, like whether it's required or a secret.
+	fn notes(&self) -> Vec<String> {
+		let mut notes = Vec::new();
+
+		if self.is_required() {
+			notes.push("required".to_string());
+		}
+		if self.sensitive {
+			notes.push("sensitive".to_string());
+		}
+		if let Some(key_file) = &self.key_file {
+			notes.push(format!("encrypted with the key in {key_file}"));
+		} else if self.encrypted {
+			notes.push("encrypted".to_string());
+		}
+		if self.from_file {
+			notes.push(format!(
+				"can be read from the file named in {}_FILE instead",
+				self.name
+			));
+		}
+		match self.kind {
+			EnvVarKind::Value | EnvVarKind::Map => (),
+			EnvVarKind::List => notes.push(format!(
+				"list; can be given as {name}_0, {name}_1, and so on instead",
+				name = self.name
+			)),
+		}
+
+		notes
+	}
+}
+
+/// The documentation for all the environment variables that a service takes, including the
+/// ones that the service skeleton itself reads.
+///
+/// ```rust
+/// use service_skeleton::{EnvDocs, ServiceConfig};
+///
+/// #[derive(Clone, Debug, ServiceConfig)]
+/// struct Config {
+///     /// Who to say hello to.
+///     #[config(default_value = "World")]
+///     name: String,
+/// }
+///
+/// let docs = EnvDocs::for_service::<Config>("hello");
+/// assert!(docs.markdown().contains("## `HELLO_NAME`"));
+/// assert!(docs.dotenv().contains("#HELLO_NAME=World"));
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct EnvDocs {
+	vars: Vec<EnvVar>,
+}
+
+impl EnvDocs {
+	/// Document the variables for a service with the given `name`, whose config is `CFG`.
+	#[must_use]
+	pub fn for_service<CFG: Service>(name: &str) -> Self {
+		let prefix = AsShoutySnekCase(name).to_string();
+
+		let mut vars = CFG::fields(&prefix);
+
+		let mut key_files: Vec<EnvVar> = Vec::new();
+		for var in &vars {
+			let Some(key_file) = &var.key_file else {
+				continue;
+			};
+			if let Some(existing) = key_files.iter_mut().find(|k| k.name == *key_file) {
+				existing.optional &= var.optional;
+			} else {
+				let mut key_var = EnvVar::new(key_file.clone(), EnvVarKind::Value, "PathBuf");
+				key_var.description =
+					"The file containing the key for decrypting encrypted config values.";
+				key_var.optional = var.optional;
+				key_files.push(key_var);
+			}
+		}
+		vars.extend(key_files);
+
+		vars.extend(SKELETON_VARS.iter().map(|&(name, type_name, description)| {
+			let mut var = EnvVar::new(format!("{prefix}_{name}"), EnvVarKind::Value, type_name);
+			var.description = description;
+			var.optional = true;
+			var
+		}));
+
+		Self { vars }
+	}
+
+	/// All the variables, with the service's own config first, then any key files, then the
+	/// skeleton's settings.
+	#[must_use]
+	pub fn vars(&self) -> &[EnvVar] {
+		&self.vars
+	}
+
+	/// A Markdown document, with a section for each variable.
+	#[must_use]
+	pub fn markdown(&self) -> String {
+		let mut lines = Vec::new();
+
+		for var in &self.vars {
+			lines.push(format!("## `{}`", var.display_name()));
+			lines.push(String::new());
+			if !var.description.is_empty() {
+				lines.push(var.description.to_string());
+				lines.push(String::new());
+			}
+			lines.push(format!("* Type: `{}`", var.type_name));
+			if let Some(default) = var.default_text() {
+				lines.push(format!("* Default: `{default}`"));
+			}
+			lines.extend(var.notes().into_iter().map(|mut note| {
+				if let Some(first) = note.get_mut(0..1) {
+					first.make_ascii_uppercase();
+				}
+				format!("* {note}")
+			}));
+			lines.push(String::new());
+		}
+
+		lines.join("\n")
+	}
+
+	/// A sample `.env` file, with every variable described in a comment, and those that don't
+	/// need to be set commented out.
+	#[must_use]
+	pub fn dotenv(&self) -> String {
+		let mut lines = Vec::new();
+
+		for var in &self.vars {
+			lines.extend(
+				var.description
+					.lines()
+					.map(|line| format!("# {line}").trim_end().to_string()),
+			);
+			let mut about = vec![var.type_name.to_string()];
+			if let Some(default_expr) = var.default_expr {
+				about.push(format!("default: {default_expr}"));
+			}
+			about.extend(var.notes());
+			lines.push(format!("# ({})", about.join("; ")));
+
+			let comment = if var.is_required() { "" } else { "#" };
+			lines.push(format!(
+				"{comment}{}={}",
+				var.display_name(),
+				var.default.unwrap_or_default()
+			));
+			lines.push(String::new());
+		}
+
+		lines.join("\n")
+	}
+
+	/// A plain-text table, with one row per variable.
+	#[must_use]
+	pub fn table(&self) -> String {
+		let header = ["VARIABLE", "TYPE", "DEFAULT", "NOTES", "DESCRIPTION"].map(String::from);
+
+		let rows: Vec<[String; 5]> = std::iter::once(header)
+			.chain(self.vars.iter().map(|var| {
+				[
+					var.display_name(),
+					var.type_name.to_string(),
+					var.default_text().unwrap_or_default().to_string(),
+					var.notes().join(", "),
+					var.summary(),
+				]
+			}))
+			.collect();
+
+		render_table(&rows)
+	}
+}
+
+/// Lay out `rows` in columns, each as wide as the widest cell in it, with the first row as the
+/// header.
+pub(crate) fn render_table<const N: usize>(rows: &[[String; N]]) -> String {
+	let mut widths = [0usize; N];
+	for row in rows {
+		for (width, cell) in widths.iter_mut().zip(row) {
+			*width = (*width).max(cell.chars().count());
+		}
+	}
+
+	rows.iter()
+		.map(|row| {
+			row.iter()
+				.zip(widths)
+				.map(|(cell, width)| format!("{cell:width$}"))
+				.collect::<Vec<_>>()
+				.join("  ")
+				.trim_end()
+				.to_string()
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
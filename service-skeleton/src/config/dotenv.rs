@@ -0,0 +1,169 @@
+//! Loading environment variables from `.env` files.
+//!
+
+use std::{collections::HashSet, env, path::Path};
+
+use crate::Error;
+
+/// Read the given `.env` file, and set every variable in it that isn't already set in the
+/// environment.  If a variable is in the file more than once, the last one wins.
+///
+/// Returns the names of the variables that were set.
+///
+pub(crate) fn load(path: &Path) -> Result<Vec<String>, Error> {
+	let display_path = path.display().to_string();
+	let contents =
+		std::fs::read_to_string(path).map_err(|e| Error::config_file_read(&display_path, e))?;
+
+	let vars = parse(&contents).map_err(|e| Error::config_file_parse(&display_path, e))?;
+
+	// Which vars were already set has to be worked out before any of them get set, or else a
+	// var that's in the file twice would look like it was set already the second time around
+	let already_set: HashSet<String> = vars
+		.iter()
+		.filter(|(k, _)| env::var_os(k).is_some())
+		.map(|(k, _)| k.clone())
+		.collect();
+
+	let mut set: Vec<String> = Vec::new();
+	for (k, v) in vars {
+		if already_set.contains(&k) {
+			continue;
+		}
+		env::set_var(&k, v);
+		if !set.contains(&k) {
+			set.push(k);
+		}
+	}
+
+	Ok(set)
+}
+
+/// Parse the contents of a `.env` file into a list of variables.
+///
+/// The format is the one that everyone's shell scripts and `docker --env-file` more-or-less
+/// agree on:
+///
+/// * `NAME=value`, optionally preceded by `export`;
+/// * blank lines, and lines starting with `#`, are ignored;
+/// * unquoted values have surrounding whitespace, and anything after a ` #`, removed;
+/// * single-quoted values are taken literally, and can span multiple lines;
+/// * double-quoted values can span multiple lines, and understand `\n`, `\t`, `\r`, `\"`, and
+///   `\\` escapes.
+///
+fn parse(contents: &str) -> Result<Vec<(String, String)>, String> {
+	let mut vars = Vec::new();
+	let mut lines = contents.lines().enumerate();
+
+	while let Some((idx, line)) = lines.next() {
+		let lineno = idx.saturating_add(1);
+		let line = line.trim_start();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let line = line
+			.strip_prefix("export")
+			.filter(|rest| rest.starts_with(char::is_whitespace))
+			.map_or(line, str::trim_start);
+
+		let Some((name, rest)) = line.split_once('=') else {
+			return Err(format!("line {lineno}: expected NAME=value"));
+		};
+		let name = name.trim_end();
+		if !valid_name(name) {
+			return Err(format!("line {lineno}: invalid variable name '{name}'"));
+		}
+
+		let rest = rest.trim_start();
+		let value = match rest.chars().next() {
+			Some(quote @ ('"' | '\'')) => {
+				let mut raw = rest.get(1..).unwrap_or_default().to_string();
+				loop {
+					if let Some(end) = closing_quote(&raw, quote) {
+						let trailer = raw.get(end.saturating_add(1)..).unwrap_or_default().trim();
+						if !(trailer.is_empty() || trailer.starts_with('#')) {
+							return Err(format!(
+								"line {lineno}: unexpected characters after closing quote"
+							));
+						}
+						raw.truncate(end);
+						break;
+					}
+					let Some((_, next)) = lines.next() else {
+						return Err(format!("line {lineno}: unterminated quoted value"));
+					};
+					raw.push('\n');
+					raw.push_str(next);
+				}
+
+				if quote == '"' {
+					unescape(&raw)
+				} else {
+					raw
+				}
+			}
+			_ => rest
+				.find(" #")
+				.or_else(|| rest.find("\t#"))
+				.and_then(|i| rest.get(..i))
+				.unwrap_or(rest)
+				.trim_end()
+				.to_string(),
+		};
+
+		vars.push((name.to_string(), value));
+	}
+
+	Ok(vars)
+}
+
+fn valid_name(name: &str) -> bool {
+	let mut chars = name.chars();
+	chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Find the byte offset of the quote that closes a quoted value, skipping over escaped quotes in
+/// double-quoted values.
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+	let mut escaped = false;
+	for (i, c) in s.char_indices() {
+		if escaped {
+			escaped = false;
+		} else if c == '\\' && quote == '"' {
+			escaped = true;
+		} else if c == quote {
+			return Some(i);
+		} else {
+			// Just another character in the value
+		}
+	}
+	None
+}
+
+fn unescape(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			match chars.next() {
+				Some('n') => out.push('\n'),
+				Some('t') => out.push('\t'),
+				Some('r') => out.push('\r'),
+				Some(escaped @ ('"' | '\\')) => out.push(escaped),
+				// Anything else isn't an escape sequence we know about, so leave it be
+				Some(other) => {
+					out.push('\\');
+					out.push(other);
+				}
+				None => out.push('\\'),
+			}
+		} else {
+			out.push(c);
+		}
+	}
+	out
+}
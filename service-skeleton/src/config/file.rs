@@ -0,0 +1,172 @@
+//! Reading config values out of TOML, YAML, or JSON files.
+//!
+
+use heck::AsShoutySnekCase;
+use serde::Deserialize;
+
+use std::{collections::BTreeMap, collections::HashMap, path::Path};
+
+use crate::Error;
+
+/// The values from a config file, keyed by the env var that each one stands in for.
+#[derive(Debug)]
+pub(crate) struct ConfigFile {
+	path: String,
+	values: HashMap<String, FileValue>,
+}
+
+#[derive(Debug)]
+struct FileValue {
+	/// Where in the file the value came from, like `db.host` or `hosts[0]`
+	key: String,
+	value: String,
+}
+
+/// Any value which could appear in a config file, in any of the supported formats.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+	Null,
+	Bool(bool),
+	Int(i64),
+	UInt(u64),
+	Float(f64),
+	String(String),
+	List(Vec<RawValue>),
+	Table(BTreeMap<String, RawValue>),
+}
+
+impl ConfigFile {
+	/// Read and parse the given file, which must contain a table (or map, or object, or whatever
+	/// the format calls it) at the top level.
+	///
+	/// Each key in the file corresponds to the env var which would have the same name, once
+	/// turned into `SHOUTY_SNAKE_CASE` and given the prefix.  Nested tables and lists have their
+	/// keys (or indexes) appended to their parent's name, so the value of `host` in the `db` table
+	/// stands in for `<PREFIX>_DB_HOST`, and the first element of the `hosts` list stands in for
+	/// `<PREFIX>_HOSTS_0`.
+	///
+	/// That goes for the keys of tables that are read into map fields, too, so their keys come
+	/// out in `SHOUTY_SNAKE_CASE`, just as they would from the environment.
+	///
+	pub(crate) fn load(path: &Path, prefix: &str) -> Result<Self, Error> {
+		let display_path = path.display().to_string();
+		let contents =
+			std::fs::read_to_string(path).map_err(|e| Error::config_file_read(&display_path, e))?;
+
+		let extension = path
+			.extension()
+			.and_then(|e| e.to_str())
+			.unwrap_or_default()
+			.to_ascii_lowercase();
+
+		let root =
+			parse(&extension, &contents).map_err(|e| Error::config_file_parse(&display_path, e))?;
+		let RawValue::Table(table) = root else {
+			return Err(Error::config_file_parse(
+				&display_path,
+				"expected a table of settings at the top level",
+			));
+		};
+
+		let mut values = HashMap::new();
+		for (key, value) in table {
+			flatten(
+				format!("{}_{}", AsShoutySnekCase(prefix), AsShoutySnekCase(&key)),
+				key,
+				value,
+				&mut values,
+			);
+		}
+
+		Ok(Self {
+			path: display_path,
+			values,
+		})
+	}
+
+	/// Where the file was read from.
+	pub(crate) fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// The value that the file has for the given env var, if any.
+	pub(crate) fn get(&self, var: &str) -> Option<&str> {
+		self.values.get(var).map(|v| v.value.as_str())
+	}
+
+	/// All the env vars that the file has values for, along with those values.
+	pub(crate) fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.values
+			.iter()
+			.map(|(var, v)| (var.as_str(), v.value.as_str()))
+	}
+
+	/// Where in the file the value for the given env var was found.
+	pub(crate) fn key_for(&self, var: &str) -> Option<&str> {
+		self.values.get(var).map(|v| v.key.as_str())
+	}
+}
+
+#[cfg_attr(
+	not(any(feature = "json", feature = "toml", feature = "yaml")),
+	allow(unused_variables)
+)]
+fn parse(extension: &str, contents: &str) -> Result<RawValue, String> {
+	match extension {
+		#[cfg(feature = "toml")]
+		"toml" => toml::from_str(contents).map_err(|e| e.to_string()),
+		#[cfg(feature = "yaml")]
+		"yaml" | "yml" => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+		#[cfg(feature = "json")]
+		"json" => serde_json::from_str(contents).map_err(|e| e.to_string()),
+		_ => Err(format!(
+			"unsupported config file type '{extension}' (expected one of {})",
+			supported_extensions().join(", ")
+		)),
+	}
+}
+
+fn supported_extensions() -> Vec<&'static str> {
+	[
+		#[cfg(feature = "toml")]
+		"toml",
+		#[cfg(feature = "yaml")]
+		"yaml",
+		#[cfg(feature = "yaml")]
+		"yml",
+		#[cfg(feature = "json")]
+		"json",
+	]
+	.to_vec()
+}
+
+fn flatten(var: String, key: String, value: RawValue, values: &mut HashMap<String, FileValue>) {
+	let scalar = match value {
+		RawValue::Null => return,
+		RawValue::Bool(b) => b.to_string(),
+		RawValue::Int(i) => i.to_string(),
+		RawValue::UInt(u) => u.to_string(),
+		RawValue::Float(f) => f.to_string(),
+		RawValue::String(s) => s,
+		RawValue::List(list) => {
+			for (i, v) in list.into_iter().enumerate() {
+				flatten(format!("{var}_{i}"), format!("{key}[{i}]"), v, values);
+			}
+			return;
+		}
+		RawValue::Table(table) => {
+			for (k, v) in table {
+				flatten(
+					format!("{var}_{}", AsShoutySnekCase(&k)),
+					format!("{key}.{k}"),
+					v,
+					values,
+				);
+			}
+			return;
+		}
+	};
+
+	values.insert(var, FileValue { key, value: scalar });
+}
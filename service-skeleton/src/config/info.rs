@@ -0,0 +1,174 @@
+//! Publishing the (non-secret parts of the) config as the `<service>_config_info` metric.
+//!
+
+use std::{
+	fmt::{Debug, Display},
+	path::Path,
+};
+
+use super::Service;
+use crate::metric;
+
+/// The name of the metric (less the service name) that carries the config in its labels.
+pub(crate) const INFO_METRIC: &str = "config_info";
+
+/// The values of a config's fields, as metric labels, along with a hash of them all.
+// This struct is not meant to be used directly; it is an implementation detail that must be made
+// public because it is used in derived code
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct ConfigInfo {
+	labels: Vec<(String, String)>,
+	hash: u64,
+	hidden: bool,
+}
+
+// FNV-1a, which is trivial, and gives the same hash on every replica, whatever it was built with
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+impl ConfigInfo {
+	fn new() -> Self {
+		Self {
+			labels: Vec::new(),
+			hash: FNV_OFFSET_BASIS,
+			hidden: false,
+		}
+	}
+
+	/// Note the value of the field `name`, within the nested config at `path` (which is empty
+	/// at the top level), as rendered by [`LabelValue`]; `None` is for an `Option` field that
+	/// isn't set.  It counts toward the hash either way, but only becomes a label if it isn't
+	/// `hidden`.
+	pub fn add(&mut self, path: &str, name: &str, value: Option<String>, hidden: bool) {
+		let name = Self::join(path, name);
+
+		let value_bytes = value
+			.iter()
+			.flat_map(|v| [b'='].into_iter().chain(v.bytes()));
+		for byte in name.bytes().chain(value_bytes).chain([0]) {
+			self.hash = (self.hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+		}
+
+		if !(hidden || self.hidden) {
+			self.labels.push((name, value.unwrap_or_default()));
+		}
+	}
+
+	/// The label value for a list or set, whose elements have already been rendered.  A set's
+	/// elements get sorted, so that the same set always looks the same, whatever order it
+	/// iterates in.
+	#[must_use]
+	pub fn list(elements: impl Iterator<Item = String>, sorted: bool) -> String {
+		let mut elements: Vec<String> = elements.collect();
+		if sorted {
+			elements.sort_unstable();
+		}
+		elements.join(",")
+	}
+
+	/// The label value for a map, whose keys and values have already been rendered, with the
+	/// entries sorted by key.
+	#[must_use]
+	pub fn map(entries: impl Iterator<Item = (String, String)>) -> String {
+		let mut entries: Vec<(String, String)> = entries.collect();
+		entries.sort_unstable();
+		entries
+			.into_iter()
+			.map(|(k, v)| format!("{k}={v}"))
+			.collect::<Vec<_>>()
+			.join(",")
+	}
+
+	/// Run `f` with every field being `hidden`, if `hidden` is set.
+	pub fn hidden_if(&mut self, hidden: bool, f: impl FnOnce(&mut Self)) {
+		let was_hidden = self.hidden;
+		self.hidden |= hidden;
+		f(self);
+		self.hidden = was_hidden;
+	}
+
+	/// The path to the field `name` within the nested config at `path`, joined with a double
+	/// underscore, which (along with a trailing underscore) the derive won't allow in a field's
+	/// name, so that `db.host` can't be mistaken for a field called `db_host`.
+	#[must_use]
+	pub fn join(path: &str, name: &str) -> String {
+		if path.is_empty() {
+			name.to_string()
+		} else {
+			format!("{path}__{name}")
+		}
+	}
+
+	/// All the labels, with the hash last.
+	fn into_labels(self) -> Vec<(String, String)> {
+		let mut labels = self.labels;
+		labels.push(("config_hash".to_string(), format!("{:016x}", self.hash)));
+		labels
+	}
+}
+
+/// A field's value (or an element of one), on its way to being a label.  Calling `label_value()`
+/// on a `&&&LabelValue`, with [`LabelViaDisplay`], [`LabelViaPath`], and [`LabelViaDebug`] all
+/// in scope, renders it with `Display` if its type has that (so strings don't get quoted or
+/// escaped), as a path if it is one (because `PathBuf` has no `Display`), and with `Debug`
+/// otherwise.
+// This struct is not meant to be used directly; it is an implementation detail that must be made
+// public because it is used in derived code
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct LabelValue<'a, T: ?Sized>(pub &'a T);
+
+// Each way of rendering is implemented for one less reference than the one before it, so that
+// method resolution tries them in order, and takes the first one that the type allows
+
+#[doc(hidden)]
+pub trait LabelViaDisplay {
+	fn label_value(&self) -> String;
+}
+
+impl<T: Display + ?Sized> LabelViaDisplay for &&LabelValue<'_, T> {
+	fn label_value(&self) -> String {
+		self.0.to_string()
+	}
+}
+
+#[doc(hidden)]
+pub trait LabelViaPath {
+	fn label_value(&self) -> String;
+}
+
+impl<T: AsRef<Path> + ?Sized> LabelViaPath for &LabelValue<'_, T> {
+	fn label_value(&self) -> String {
+		self.0.as_ref().display().to_string()
+	}
+}
+
+#[doc(hidden)]
+pub trait LabelViaDebug {
+	fn label_value(&self) -> String;
+}
+
+impl<T: Debug + ?Sized> LabelViaDebug for LabelValue<'_, T> {
+	fn label_value(&self) -> String {
+		format!("{:?}", self.0)
+	}
+}
+
+/// The labels for the `<service>_config_info` metric, for the given config: one for each field
+/// that isn't `sensitive`, `encrypted`, or `hidden`, and `config_hash`, which is a hash of the
+/// values of all the fields that aren't secret (including the `hidden` ones).
+#[must_use]
+pub fn info_labels<CFG: Service>(cfg: &CFG) -> Vec<(String, String)> {
+	let mut info = ConfigInfo::new();
+	cfg.config_info("", &mut info);
+	info.into_labels()
+}
+
+/// Set the `<service>_config_info` metric to describe `cfg`, replacing whatever it described
+/// before, if the service has that metric.
+pub(crate) fn publish_info<CFG: Service>(cfg: &CFG) {
+	if metric::is_registered(INFO_METRIC) {
+		metric::set_info(INFO_METRIC, &info_labels(cfg));
+	}
+}
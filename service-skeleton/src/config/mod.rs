@@ -0,0 +1,886 @@
+use heck::AsShoutySnekCase;
+use secrecy::{zeroize::Zeroizing, SecretString};
+use std::{
+	any::type_name,
+	cell::RefCell,
+	collections::{BTreeMap, HashMap, HashSet},
+	fmt::{self, Debug, Display, Formatter},
+	path::Path,
+};
+
+use crate::Error;
+
+pub(crate) mod dotenv;
+
+pub(crate) mod cli;
+
+mod docs;
+pub(crate) use docs::render_table;
+pub use docs::{EnvDocs, EnvVar, EnvVarKind};
+
+mod file;
+use file::ConfigFile;
+
+mod info;
+pub use info::{info_labels, ConfigInfo, LabelValue, LabelViaDebug, LabelViaDisplay, LabelViaPath};
+pub(crate) use info::{publish_info, INFO_METRIC};
+
+pub mod parsers;
+
+mod reload;
+pub use reload::Reloadable;
+pub(crate) use reload::RELOADS_METRIC;
+
+mod unknown;
+
+mod validate;
+pub use validate::{
+	validate_non_empty, validate_one_of, validate_range, validate_regex, validate_with,
+};
+
+pub trait Service {
+	/// Build the config from the given environment variables, along with the config file named
+	/// in `<PREFIX>_CONFIG_FILE`, if that variable is set.
+	///
+	/// Values from the environment take precedence over values from the file.
+	///
+	/// Any env vars that start with `<PREFIX>_`, but which the config doesn't use, are logged as
+	/// a warning (along with the name that was probably meant), as they're most likely typos.  If
+	/// the config is derived with `#[config(deny_unknown_vars)]`, they're an error instead.
+	///
+	fn from_env_vars(
+		prefix: &str,
+		vars: impl Iterator<Item = (String, String)>,
+	) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		load(prefix, vars).map(|(cfg, _)| cfg)
+	}
+
+	/// Build the config from the given environment variables and command-line arguments (less
+	/// the program name), along with the config file named in `<PREFIX>_CONFIG_FILE`, if that
+	/// variable is set.
+	///
+	/// If the config is derived with `#[config(cli)]`, every field can be set with a flag named
+	/// after its env var, less the prefix, in `kebab-case`; so `<PREFIX>_DB_HOST` can be set
+	/// with `--db-host <value>` or `--db-host=<value>`.  Flags take precedence over the
+	/// environment.  Arguments that aren't flags for one of the config's fields are ignored.
+	///
+	/// Otherwise, `args` are ignored entirely, and this is the same as
+	/// [`from_env_vars`](Self::from_env_vars).
+	///
+	/// # Errors
+	///
+	/// As for [`from_env_vars`](Self::from_env_vars), and also if a flag is given for a
+	/// `sensitive` field, because anyone who can run `ps` can see the command line.
+	///
+	fn from_env_vars_and_args(
+		prefix: &str,
+		vars: impl Iterator<Item = (String, String)>,
+		args: impl Iterator<Item = String>,
+	) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let (vars, _flags) = cli::vars_with_args::<Self>(prefix, vars, args)?;
+		Self::from_env_vars(prefix, vars.into_iter())
+	}
+
+	/// Whether the config's fields can be set with command-line flags.
+	#[doc(hidden)]
+	#[must_use]
+	fn cli_flags() -> bool
+	where
+		Self: Sized,
+	{
+		false
+	}
+
+	// This is what the derived implementation provides, so it can work with every source of
+	// config values, not just env vars
+	#[doc(hidden)]
+	fn from_sources(prefix: &str, sources: &mut Sources) -> Result<Self, Error>
+	where
+		Self: Sized;
+
+	/// Whether the config can be reloaded while the service is running, which means the service
+	/// needs to keep count of the reloads.
+	#[doc(hidden)]
+	#[must_use]
+	fn reloadable() -> bool
+	where
+		Self: Sized,
+	{
+		false
+	}
+
+	/// Whether env vars that look like they're meant for this config, but aren't used by it, are
+	/// an error, rather than just worth a warning.
+	#[doc(hidden)]
+	#[must_use]
+	fn deny_unknown_vars() -> bool
+	where
+		Self: Sized,
+	{
+		false
+	}
+
+	/// Describe the config's fields: for each one, the env var that it's read from (given the
+	/// prefix that would be passed to [`from_env_vars`](Self::from_env_vars)), its type and
+	/// default, whether it's optional, `sensitive`, or `encrypted`, the env var that names its
+	/// key file, and its doc comment.
+	///
+	/// The fields of `nested` and `flatten`ed configs are described individually, with their
+	/// [`field`](EnvVar::field) being the path to them, like `db.host`.  Every one of them has
+	/// its `field` set; the key files, and the skeleton's own settings, which aren't read into a
+	/// field, are only listed by [`EnvDocs`].
+	///
+	/// The default implementation doesn't know of any fields.
+	///
+	/// ```rust
+	/// use service_skeleton::ServiceConfig;
+	///
+	/// #[derive(Clone, Debug, ServiceConfig)]
+	/// struct Config {
+	///     /// Who to say hello to.
+	///     #[config(default_value = "World")]
+	///     name: String,
+	/// }
+	///
+	/// let fields = Config::fields("hello");
+	/// assert_eq!(Some("name"), fields[0].field.as_deref());
+	/// assert_eq!("HELLO_NAME", fields[0].name);
+	/// assert_eq!("String", fields[0].type_name);
+	/// assert_eq!(Some("World"), fields[0].default);
+	/// assert_eq!("Who to say hello to.", fields[0].description);
+	/// ```
+	///
+	#[must_use]
+	fn fields(_prefix: &str) -> Vec<EnvVar>
+	where
+		Self: Sized,
+	{
+		Vec::new()
+	}
+
+	/// Add the values of the config's fields to `info`, for the `<service>_config_info` metric,
+	/// with their names prefixed by `path`.
+	///
+	/// The default implementation doesn't add any.
+	#[doc(hidden)]
+	fn config_info(&self, _path: &str, _info: &mut ConfigInfo) {}
+
+	/// Write out the config in the style of `Debug`, but with the values of all `sensitive` and
+	/// `encrypted` fields masked.
+	///
+	/// The derived implementation shows every field; the default implementation shows none of
+	/// them, because it can't know which ones are safe to show.
+	fn fmt_redacted(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct(type_name::<Self>()).finish_non_exhaustive()
+	}
+
+	/// Wrap the config in something whose `Debug` implementation doesn't leak secrets.
+	fn redacted(&self) -> Redacted<'_, Self>
+	where
+		Self: Sized,
+	{
+		Redacted(self)
+	}
+}
+
+/// Build a config from the given env vars (and config file), checking for unknown vars, and
+/// hand back the sources it was built from, too.
+pub(crate) fn load<CFG: Service>(
+	prefix: &str,
+	vars: impl Iterator<Item = (String, String)>,
+) -> Result<(CFG, Sources), Error> {
+	load_from(prefix, Sources::new(prefix, vars)?)
+}
+
+/// As for [`load`], but from `sources` that have already been collected.
+pub(crate) fn load_from<CFG: Service>(
+	prefix: &str,
+	mut sources: Sources,
+) -> Result<(CFG, Sources), Error> {
+	let cfg = CFG::from_sources(prefix, &mut sources);
+
+	let unknown = unknown::unknown_vars(&sources, prefix);
+	if CFG::deny_unknown_vars() && !unknown.is_empty() {
+		let errors = cfg.err().into_iter().chain(unknown).collect();
+		return Err(Error::config_errors(errors));
+	}
+	for e in unknown {
+		tracing::warn!("{e}");
+	}
+
+	Ok((cfg?, sources))
+}
+
+impl Service for () {
+	fn from_sources(_prefix: &str, _sources: &mut Sources) -> Result<Self, Error> {
+		Ok(())
+	}
+
+	fn fmt_redacted(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("()")
+	}
+}
+
+/// A config whose `Debug` output has the values of `sensitive` and `encrypted` fields masked.
+///
+/// Get one of these by calling [`redacted`](Service::redacted) on your config.
+pub struct Redacted<'a, T: Service>(&'a T);
+
+impl<T: Service> Debug for Redacted<'_, T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.0.fmt_redacted(f)
+	}
+}
+
+/// Everywhere that config values can come from: the environment, and (optionally) a config
+/// file.
+// This struct is not meant to be used directly; it is an implementation detail that must be made
+// public because it is used in derived code
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Sources {
+	/// Wiped when we're done with them, because some of them are bound to be secrets
+	vars: HashMap<String, Zeroizing<String>>,
+	file: Option<ConfigFile>,
+	keys: HashMap<Key, SecretString>,
+	all_sensitive: bool,
+	/// Every var that the config has looked for, so we can tell which ones it didn't
+	known: RefCell<HashSet<String>>,
+	/// The prefixes of every group of vars that the config has looked for, like map fields,
+	/// along with the vars under each prefix that belong to other fields
+	known_prefixes: RefCell<HashMap<String, Vec<String>>>,
+	/// Whether sensitive vars get removed from the process environment once they've been read
+	purge_env: bool,
+	/// The skeleton's own settings, like `<PREFIX>_LOG_SINK`, which are never a map's entries
+	skeleton_vars: Vec<String>,
+	/// The vars of the fields outside the nested configs currently being read, which the map
+	/// fields within them have to leave alone
+	outer_vars: Vec<String>,
+	/// The vars whose values were given by command-line flags, along with the flag for each
+	flags: HashMap<String, String>,
+}
+
+impl Sources {
+	/// Collect up the given env vars, and read the config file named in `<PREFIX>_CONFIG_FILE`,
+	/// if there is one.
+	///
+	/// # Errors
+	///
+	/// If the config file can't be read or parsed.
+	///
+	pub fn new(prefix: &str, vars: impl Iterator<Item = (String, String)>) -> Result<Self, Error> {
+		let vars: HashMap<String, Zeroizing<String>> = vars
+			.map(|(var, value)| (var, Zeroizing::new(value)))
+			.collect();
+
+		let file = vars
+			.get(&format!("{}_CONFIG_FILE", AsShoutySnekCase(prefix)))
+			.map(|path| ConfigFile::load(Path::new(path.as_str()), prefix))
+			.transpose()?;
+
+		Ok(Self {
+			vars,
+			file,
+			keys: HashMap::new(),
+			all_sensitive: false,
+			known: RefCell::new(HashSet::new()),
+			known_prefixes: RefCell::new(HashMap::new()),
+			purge_env: true,
+			skeleton_vars: docs::SKELETON_VARS
+				.iter()
+				.map(|(name, _, _)| format!("{}_{name}", AsShoutySnekCase(prefix)))
+				.collect(),
+			outer_vars: Vec::new(),
+			flags: HashMap::new(),
+		})
+	}
+
+	/// Note which of the vars had their values given by command-line flags (rather than being
+	/// set in the environment), and what the flag for each one was.
+	#[must_use]
+	pub(crate) fn with_flags(mut self, flags: HashMap<String, String>) -> Self {
+		self.flags = flags;
+		self
+	}
+
+	/// Run `f` to read a nested config, whose map fields have to leave the `vars` of the fields
+	/// outside it (as well as those outside its parent, and so on) alone.
+	pub fn with_outer_vars<T>(&mut self, vars: Vec<String>, f: impl FnOnce(&mut Self) -> T) -> T {
+		let outer_len = self.outer_vars.len();
+		self.outer_vars.extend(vars);
+		let result = f(self);
+		self.outer_vars.truncate(outer_len);
+		result
+	}
+
+	/// Whether `var` belongs to something other than the map field whose `siblings` are given:
+	/// one of those siblings, or one of the skeleton's own settings.
+	fn belongs_elsewhere(&self, var: &str, siblings: &[String]) -> bool {
+		is_sibling_var(var, siblings) || self.skeleton_vars.iter().any(|s| s == var)
+	}
+
+	/// Leave the process environment alone, rather than removing sensitive vars from it once
+	/// they've been read; for when the vars are a snapshot that's being read again, which can
+	/// happen on any thread, while the rest of the service is running (and so isn't the time to
+	/// be changing the environment).
+	#[must_use]
+	pub(crate) fn without_purging(mut self) -> Self {
+		self.purge_env = false;
+		self
+	}
+
+	/// Whether sensitive vars should be removed from the process environment once they've been
+	/// read.
+	#[must_use]
+	pub fn purges_env(&self) -> bool {
+		self.purge_env
+	}
+
+	/// Whether every field should be treated as `sensitive`, because the field that the config
+	/// currently being read is nested within is.
+	#[must_use]
+	pub fn all_sensitive(&self) -> bool {
+		self.all_sensitive
+	}
+
+	/// Run `f` with every field being treated as `sensitive`.
+	pub fn sensitively<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+		let was_sensitive = std::mem::replace(&mut self.all_sensitive, true);
+		let result = f(self);
+		self.all_sensitive = was_sensitive;
+		result
+	}
+
+	/// The value for the given env var, from the environment if it's set there, otherwise from
+	/// the config file.
+	#[must_use]
+	pub fn get(&self, var: &str) -> Option<&str> {
+		self.mark_known(var);
+		self.value(var)
+	}
+
+	/// The value for the given env var, without noting that the config uses it.
+	fn value(&self, var: &str) -> Option<&str> {
+		self.vars
+			.get(var)
+			.map(|value| value.as_str())
+			.or_else(|| self.file.as_ref().and_then(|f| f.get(var)))
+	}
+
+	/// All the vars whose names start with `prefix`, along with their values, from the
+	/// environment or the config file (with the environment taking precedence, as always).
+	///
+	/// Vars that belong to one of the `siblings` (by being one of them, or starting with one of
+	/// them and an underscore) are left out, so that a map field called `labels` doesn't take
+	/// the value of a `labels_extra` field as one of its entries.
+	#[must_use]
+	pub fn with_prefix(&self, prefix: &str, siblings: &[String]) -> BTreeMap<&str, &str> {
+		self.known_prefixes
+			.borrow_mut()
+			.insert(prefix.to_string(), siblings.to_vec());
+
+		self.entries(prefix, siblings)
+	}
+
+	/// The vars found by [`with_prefix`](Self::with_prefix), without noting that the config uses
+	/// them.
+	fn entries(&self, prefix: &str, siblings: &[String]) -> BTreeMap<&str, &str> {
+		let mut found: BTreeMap<&str, &str> = self
+			.file
+			.iter()
+			.flat_map(ConfigFile::vars)
+			.filter(|(var, _)| var.starts_with(prefix) && !self.belongs_elsewhere(var, siblings))
+			.collect();
+		found.extend(
+			self.vars
+				.iter()
+				.filter(|(var, _)| {
+					var.starts_with(prefix) && !self.belongs_elsewhere(var, siblings)
+				})
+				.map(|(var, value)| (var.as_str(), value.as_str())),
+		);
+		found
+	}
+
+	/// Note that `var` is used by the config, even if it hasn't been (and might never be) looked
+	/// at.
+	fn mark_known(&self, var: &str) {
+		self.known.borrow_mut().insert(var.to_string());
+	}
+
+	/// Whether `var` has been looked at, or is otherwise known to be used by the config.
+	fn is_known(&self, var: &str) -> bool {
+		self.known.borrow().contains(var)
+			|| self
+				.known_prefixes
+				.borrow()
+				.iter()
+				.any(|(prefix, siblings)| {
+					var.starts_with(prefix.as_str()) && !self.belongs_elsewhere(var, siblings)
+				})
+	}
+
+	/// All the vars that are known to be used by the config.
+	fn known_vars(&self) -> Vec<String> {
+		self.known.borrow().iter().cloned().collect()
+	}
+
+	/// The names of all the env vars (but not the ones from the config file, if any).
+	fn env_vars(&self) -> impl Iterator<Item = &str> {
+		self.vars.keys().map(String::as_str)
+	}
+
+	/// The env vars (but not the values from the config file, if any) that the config uses,
+	/// along with `<PREFIX>_CONFIG_FILE`, so the config can be read again later.
+	pub(crate) fn env_snapshot(&self, prefix: &str) -> Vec<(String, Zeroizing<String>)> {
+		let config_file = format!("{}_CONFIG_FILE", AsShoutySnekCase(prefix));
+
+		self.vars
+			.iter()
+			.filter(|(var, _)| **var == config_file || self.is_known(var))
+			.map(|(var, value)| (var.clone(), value.clone()))
+			.collect()
+	}
+
+	/// The value of every var that the config is known to use, from wherever it came from, so
+	/// that we can tell what changed when the config is read again.
+	pub(crate) fn known_values(&self) -> BTreeMap<String, Zeroizing<String>> {
+		let mut values: BTreeMap<String, Zeroizing<String>> = self
+			.known_vars()
+			.into_iter()
+			.filter_map(|var| {
+				let value = Zeroizing::new(self.value(&var)?.to_string());
+				Some((var, value))
+			})
+			.collect();
+
+		let prefixes: Vec<(String, Vec<String>)> = self
+			.known_prefixes
+			.borrow()
+			.iter()
+			.map(|(prefix, siblings)| (prefix.clone(), siblings.clone()))
+			.collect();
+		for (prefix, siblings) in prefixes {
+			values.extend(
+				self.entries(&prefix, &siblings)
+					.into_iter()
+					.map(|(var, value)| (var.to_string(), Zeroizing::new(value.to_string()))),
+			);
+		}
+
+		values
+	}
+
+	/// Where the value for the given variable is coming from, or `None` if it isn't set
+	/// anywhere.
+	pub(crate) fn origin(&self, var: &EnvVar) -> Option<Source> {
+		let names = match var.kind {
+			EnvVarKind::Value if var.from_file => {
+				vec![var.name.clone(), format!("{}_FILE", var.name)]
+			}
+			EnvVarKind::Value => vec![var.name.clone()],
+			EnvVarKind::List => vec![var.name.clone(), format!("{}_0", var.name)],
+			EnvVarKind::Map => {
+				let prefix = format!("{}_", var.name);
+				let siblings = self
+					.known_prefixes
+					.borrow()
+					.get(&prefix)
+					.cloned()
+					.unwrap_or_default();
+				self.entries(&prefix, &siblings)
+					.into_keys()
+					.map(str::to_string)
+					.collect()
+			}
+		};
+
+		names.iter().find_map(|name| {
+			if let Some(flag) = self.flags.get(name) {
+				Some(Source::Flag(flag.clone()))
+			} else if self.vars.contains_key(name) {
+				Some(Source::Environment(name.clone()))
+			} else {
+				let file = self.file.as_ref()?;
+				file.key_for(name).map(|key| Source::ConfigFile {
+					path: file.path().to_string(),
+					key: key.to_string(),
+				})
+			}
+		})
+	}
+
+	/// An error saying that the value for `var` couldn't be parsed, which points to the right
+	/// place in the config file if that's where the value came from.
+	pub fn parse_error(&self, var: &str, cause: impl Display) -> Error {
+		if !self.vars.contains_key(var) {
+			if let Some(file) = &self.file {
+				if let Some(key) = file.key_for(var) {
+					return Error::config_file_value_parse(file.path(), key, cause);
+				}
+			}
+		}
+
+		Error::config_value_parse(var, cause)
+	}
+}
+
+/// Where a config value came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Source {
+	/// The given command-line flag
+	Flag(String),
+	/// The given env var
+	Environment(String),
+	/// The given key in the config file at `path`
+	ConfigFile { path: String, key: String },
+}
+
+impl Display for Source {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Flag(flag) => write!(f, "command line ({flag})"),
+			Self::Environment(var) => write!(f, "environment ({var})"),
+			Self::ConfigFile { path, key } => write!(f, "config file {path} ({key})"),
+		}
+	}
+}
+
+pub fn determine_value<RT: Debug + Sync + Send, E: Display>(
+	sources: &Sources,
+	var: &str,
+	parser: impl Fn(&str) -> Result<RT, E>,
+	value: Option<&str>,
+	default: Option<&'static str>,
+) -> Result<RT, Error> {
+	match (value, default) {
+		(Some(value), _) => parser(value).map_err(|e| sources.parse_error(var, e)),
+		(None, Some(default_value)) => {
+			parser(default_value).map_err(|e| Error::config_value_parse(var, e))
+		}
+		(None, None) => Err(Error::no_config_value(var)),
+	}
+}
+
+pub fn determine_optional_value<RT: Debug + Sync + Send, E: Display>(
+	sources: &Sources,
+	var: &str,
+	parser: impl Fn(&str) -> Result<RT, E>,
+	value: Option<&str>,
+	default: Option<&'static str>,
+) -> Result<Option<RT>, Error> {
+	if value.is_none() && default.is_none() {
+		return Ok(None);
+	}
+
+	determine_value(sources, var, parser, value, default).map(Some)
+}
+
+pub fn determine_list<C: FromIterator<T>, T, E: Display>(
+	sources: &Sources,
+	var: &str,
+	separator: &str,
+	parser: impl Fn(&str) -> Result<T, E>,
+	default: Option<&'static str>,
+) -> Result<C, Error> {
+	determine_optional_list(sources, var, separator, parser, default)?
+		.ok_or_else(|| Error::no_config_value(var))
+}
+
+/// Figure out the elements of a list-like field.
+///
+/// If `var` itself is set, its value is split on `separator`, and each non-empty element parsed.
+/// Otherwise, the elements are taken from `<var>_0`, `<var>_1`, and so on, stopping at the first
+/// one that isn't set.  If there aren't any of those either, then the default (if any) is split
+/// and parsed.
+///
+pub fn determine_optional_list<C: FromIterator<T>, T, E: Display>(
+	sources: &Sources,
+	var: &str,
+	separator: &str,
+	parser: impl Fn(&str) -> Result<T, E>,
+	default: Option<&'static str>,
+) -> Result<Option<C>, Error> {
+	if let Some(value) = sources.get(var) {
+		return split_list(value, separator)
+			.map(|item| parser(item).map_err(|e| sources.parse_error(var, e)))
+			.collect::<Result<C, Error>>()
+			.map(Some);
+	}
+
+	let mut items = Vec::new();
+	for i in 0usize.. {
+		let item_var = format!("{var}_{i}");
+		let Some(item) = sources.get(&item_var) else {
+			break;
+		};
+		items.push(parser(item).map_err(|e| sources.parse_error(&item_var, e))?);
+	}
+	if !items.is_empty() {
+		return Ok(Some(items.into_iter().collect()));
+	}
+
+	default
+		.map(|default_value| {
+			split_list(default_value, separator)
+				.map(|item| parser(item).map_err(|e| Error::config_value_parse(var, e)))
+				.collect::<Result<C, Error>>()
+		})
+		.transpose()
+}
+
+fn split_list<'a>(value: &'a str, separator: &'a str) -> impl Iterator<Item = &'a str> {
+	value
+		.split(separator)
+		.map(str::trim)
+		.filter(|item| !item.is_empty())
+}
+
+pub fn determine_map<C: FromIterator<(K, V)>, K, V, KE: Display, VE: Display>(
+	sources: &Sources,
+	var: &str,
+	siblings: &[String],
+	key_parser: impl Fn(&str) -> Result<K, KE>,
+	value_parser: impl Fn(&str) -> Result<V, VE>,
+) -> Result<C, Error> {
+	let prefix = format!("{var}_");
+
+	sources
+		.with_prefix(&prefix, siblings)
+		.into_iter()
+		.map(|(entry_var, value)| {
+			let key = entry_var.strip_prefix(&prefix).unwrap_or(entry_var);
+			Ok((
+				key_parser(key).map_err(|e| sources.parse_error(entry_var, e))?,
+				value_parser(value).map_err(|e| sources.parse_error(entry_var, e))?,
+			))
+		})
+		.collect()
+}
+
+/// Figure out the entries of a map-like field, from all the `<var>_<KEY>` vars, or `None` if
+/// there aren't any.
+pub fn determine_optional_map<C: FromIterator<(K, V)>, K, V, KE: Display, VE: Display>(
+	sources: &Sources,
+	var: &str,
+	siblings: &[String],
+	key_parser: impl Fn(&str) -> Result<K, KE>,
+	value_parser: impl Fn(&str) -> Result<V, VE>,
+) -> Result<Option<C>, Error> {
+	if sources.with_prefix(&format!("{var}_"), siblings).is_empty() {
+		Ok(None)
+	} else {
+		determine_map(sources, var, siblings, key_parser, value_parser).map(Some)
+	}
+}
+
+/// Whether `var` belongs to one of the `siblings`, by being one of them, or by starting with one
+/// of them followed by an underscore (as the elements of lists, and the fields of nested
+/// configs, do).
+fn is_sibling_var(var: &str, siblings: &[String]) -> bool {
+	siblings.iter().any(|sibling| {
+		var.strip_prefix(sibling.as_str())
+			.is_some_and(|rest| rest.is_empty() || rest.starts_with('_'))
+	})
+}
+
+/// The vars read by all the fields of a config other than `field`, from the `(field, vars)`
+/// pairs of all of its fields, along with those of the fields outside the config, if it's
+/// nested; which are the `siblings` of a map field, or the outer vars of a nested one.
+#[must_use]
+pub fn sibling_vars(
+	sources: &Sources,
+	field_vars: &[(&str, Vec<String>)],
+	field: &str,
+) -> Vec<String> {
+	field_vars
+		.iter()
+		.filter(|(name, _)| *name != field)
+		.flat_map(|(_, vars)| vars.iter().cloned())
+		.chain(sources.outer_vars.iter().cloned())
+		.collect()
+}
+
+/// Remove the env vars that might have held the elements of a sensitive list field: `var`
+/// itself, and `<var>_0`, `<var>_1`, and so on.
+pub fn purge_list(var: &str) {
+	for (name, _) in std::env::vars_os() {
+		if name.to_str().is_some_and(|n| {
+			n.strip_prefix(var).is_some_and(|rest| {
+				rest.is_empty()
+					|| rest == "_FILE"
+					|| rest
+						.strip_prefix('_')
+						.is_some_and(|i| !i.is_empty() && i.bytes().all(|b| b.is_ascii_digit()))
+			})
+		}) {
+			::tracing::debug!("Removing sensitive env var {}", name.to_string_lossy());
+			std::env::remove_var(&name);
+		}
+	}
+}
+
+/// Remove the env vars that might have held the entries of a sensitive map field, which are
+/// all the `<var>_<KEY>` vars, other than those that belong to its `siblings` (or to the
+/// skeleton).
+pub fn purge_map(sources: &Sources, var: &str, siblings: &[String]) {
+	let prefix = format!("{var}_");
+	for (name, _) in std::env::vars_os() {
+		if name
+			.to_str()
+			.is_some_and(|n| n.starts_with(&prefix) && !sources.belongs_elsewhere(n, siblings))
+		{
+			::tracing::debug!("Removing sensitive env var {}", name.to_string_lossy());
+			std::env::remove_var(&name);
+		}
+	}
+}
+
+/// Get the value out of `f`'s result, or squirrel away the error for reporting later, so that
+/// one bad field doesn't stop the rest of them from being checked.
+pub fn collect_error<T>(
+	errors: &mut Vec<Error>,
+	f: impl FnOnce() -> Result<T, Error>,
+) -> Option<T> {
+	f().map_err(|e| errors.push(e)).ok()
+}
+
+/// Every problem with the `default_value`s of `CFG`'s fields (and those of any configs nested
+/// within it), found by reading the config from an empty environment, so that every default gets
+/// used.
+///
+/// The derive macro generates a test which calls this, for configs with defaults that can't be
+/// checked at compile time.
+#[must_use]
+pub fn default_value_problems<CFG: Service>(prefix: &str) -> Vec<String> {
+	let Err(e) = Sources::new(prefix, std::iter::empty())
+		.and_then(|mut sources| CFG::from_sources(prefix, &mut sources))
+	else {
+		return Vec::new();
+	};
+
+	e.problems()
+		.into_iter()
+		// Anything else, like a missing value for a field with no default, isn't the default's
+		// fault
+		.filter(|problem| {
+			matches!(
+				problem,
+				Error::ConfigValueParse { .. } | Error::ConfigValueInvalid { .. }
+			)
+		})
+		.map(ToString::to_string)
+		.collect()
+}
+
+/// Figure out which env var a field's value should be read from.
+///
+/// That's `var`, unless `var` isn't set but one of the field's (deprecated) `aliases` is, in
+/// which case it's the first alias that is set, and we'll nag about it.  What "is set" means
+/// depends on the kind of field, so the caller tells us.
+///
+#[must_use]
+pub fn resolve_var(
+	sources: &Sources,
+	var: String,
+	aliases: &[&str],
+	is_set: impl Fn(&Sources, &str) -> bool,
+) -> String {
+	for alias in aliases {
+		sources.mark_known(alias);
+	}
+
+	if is_set(sources, &var) {
+		return var;
+	}
+
+	aliases
+		.iter()
+		.find(|alias| is_set(sources, alias))
+		.map_or(var.clone(), |alias| {
+			tracing::warn!("{alias} is deprecated; please set {var} instead");
+			(*alias).to_string()
+		})
+}
+
+/// Fetch the value for `var`, either from `var` itself, or from the file named in `<var>_FILE`.
+///
+/// The files are usually there to hold secrets, so neither the value nor what's read from the
+/// file is left lying around in memory once we're done with them.
+pub fn fetch_from_file(sources: &Sources, var: &str) -> Result<Option<Zeroizing<String>>, Error> {
+	let file_var = format!("{var}_FILE");
+
+	match (sources.get(var), sources.get(&file_var)) {
+		(Some(_), Some(_)) => Err(Error::config_value_conflict(var, &file_var)),
+		(Some(value), None) => Ok(Some(Zeroizing::new(value.to_string()))),
+		(None, Some(file)) => std::fs::read_to_string(file)
+			.map(Zeroizing::new)
+			.map(|contents| Some(Zeroizing::new(contents.trim().to_string())))
+			.map_err(|e| Error::config_value_file_read(var, file, e)),
+		(None, None) => Ok(None),
+	}
+}
+
+pub fn fetch_encrypted_field(
+	sources: &mut Sources,
+	value_field_var: &str,
+	value: Option<Zeroizing<String>>,
+	key_spec: &Key,
+) -> Result<Option<SecretString>, Error> {
+	let Key::File(ref file_env) = key_spec;
+	sources.mark_known(file_env);
+
+	let Some(value) = value else {
+		return Ok(None);
+	};
+
+	// Take the key out of the cache while we're using it, so we can look at the other sources
+	// if we have to go and get it
+	let key = match sources.keys.remove(key_spec) {
+		Some(k) => k,
+		None => match key_spec {
+			Key::File(ref file_env) => {
+				let Some(key_file) = sources.get(file_env) else {
+					return Err(Error::no_config_value(file_env));
+				};
+				read_key(key_file)?
+			}
+		},
+	};
+
+	let decrypted = sscrypt::decrypt_secret(&value, value_field_var, &key)
+		.map_err(|e| sources.parse_error(value_field_var, e));
+	sources.keys.insert(key_spec.clone(), key);
+
+	decrypted.map(Some)
+}
+
+/// Read a private key from `key_file`, without leaving a copy of it lying around in memory that
+/// won't be wiped.
+fn read_key(key_file: &str) -> Result<SecretString, Error> {
+	let contents =
+		Zeroizing::new(std::fs::read(key_file).map_err(|e| Error::key_read(key_file, e))?);
+	let key = std::str::from_utf8(&contents).map_err(|e| {
+		Error::key_read(
+			key_file,
+			std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+		)
+	})?;
+
+	Ok(key.trim_end().to_string().into())
+}
+
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+#[non_exhaustive]
+// This enum is not meant to be used directly; it is an implementation detail that must be made
+// public because it is used in derived code
+#[doc(hidden)]
+pub enum Key {
+	File(String),
+}
@@ -0,0 +1,282 @@
+//! Parsers for the sorts of values that services often need, but which can't be (sensibly) read
+//! with `FromStr`.
+//!
+//! Use them as a field's `value_parser`:
+//!
+//! ```rust
+//! use service_skeleton::{config::parsers, ServiceConfig};
+//! use std::{net::SocketAddr, time::Duration};
+//!
+//! #[derive(Clone, Debug, ServiceConfig)]
+//! struct Config {
+//!     // Duration fields use parsers::duration without having to be told
+//!     #[config(default_value = "30s")]
+//!     timeout: Duration,
+//!
+//!     #[config(default_value = "10MiB", value_parser = parsers::byte_size)]
+//!     max_body: u64,
+//!
+//!     #[config(default_value = "yes", value_parser = parsers::boolean)]
+//!     enabled: bool,
+//!
+//!     #[config(default_value = "127.0.0.1", value_parser = parsers::socket_addr::<8080>)]
+//!     listen: SocketAddr,
+//! }
+//!
+//! let cfg = Config::from_env_vars("parsed", std::iter::empty()).unwrap();
+//! assert_eq!(Duration::from_secs(30), cfg.timeout);
+//! assert_eq!(10 * 1024 * 1024, cfg.max_body);
+//! assert!(cfg.enabled);
+//! assert_eq!(8080, cfg.listen.port());
+//! ```
+//!
+//! Every parser returns a `String` describing what was wrong with the value, which ends up in
+//! the error that says which variable it was.
+//!
+
+use std::{
+	net::{IpAddr, Ipv6Addr, SocketAddr},
+	time::Duration,
+};
+
+#[cfg(feature = "url")]
+pub use ::url::Url;
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// A duration, like `30s`, `500ms`, `1h30m`, or `1.5d`.
+///
+/// The units are `ns`, `us` (or `µs`, with either a micro sign or a Greek mu), `ms`, `s`, `m`,
+/// `h`, `d`, and `w`, and the longer forms of them (`sec`, `secs`, `seconds`, `min`, `minutes`,
+/// `hours`, and so on) are fine too.  A number on its own is a number of seconds.
+///
+/// # Errors
+///
+/// If the value isn't a duration, or is too long to fit in a `Duration`'s worth of nanoseconds
+/// (more than about 584 years, which is probably a mistake anyway).
+///
+pub fn duration(s: &str) -> Result<Duration, String> {
+	let s = s.trim();
+	if s.is_empty() {
+		return Err("expected a duration, like 30s or 1h30m".to_string());
+	}
+
+	// A bare number is some seconds
+	if s.chars().all(|c| c.is_ascii_digit() || c == '.') {
+		return nanos_to_duration(scale(s, NANOS_PER_SECOND)?);
+	}
+
+	let mut total: u128 = 0;
+	let mut rest = s;
+	while !rest.is_empty() {
+		let (number, after) = split_number(rest);
+		if number.is_empty() {
+			return Err(format!("expected a number at {rest:?}"));
+		}
+		let (unit, after) = split_unit(after.trim_start());
+		let per_unit = duration_unit(unit).ok_or_else(|| {
+			if unit.is_empty() {
+				format!("missing unit after {number}")
+			} else {
+				format!("unknown unit {unit:?} (expected one of ns, us, ms, s, m, h, d, or w)")
+			}
+		})?;
+
+		total = total
+			.checked_add(scale(number, per_unit)?)
+			.ok_or_else(too_long)?;
+		rest = after.trim_start();
+	}
+
+	nanos_to_duration(total)
+}
+
+/// The number of nanoseconds in one of the given unit.
+fn duration_unit(unit: &str) -> Option<u128> {
+	Some(match unit.to_ascii_lowercase().as_str() {
+		"ns" | "nsec" | "nanosecond" | "nanoseconds" => 1,
+		// Both the micro sign and the Greek mu, which is what most keyboards give
+		"us" | "\u{b5}s" | "\u{3bc}s" | "usec" | "microsecond" | "microseconds" => 1_000,
+		"ms" | "msec" | "millisecond" | "milliseconds" => 1_000_000,
+		"s" | "sec" | "secs" | "second" | "seconds" => NANOS_PER_SECOND,
+		"m" | "min" | "mins" | "minute" | "minutes" => 60_000_000_000,
+		"h" | "hr" | "hrs" | "hour" | "hours" => 3_600_000_000_000,
+		"d" | "day" | "days" => 86_400_000_000_000,
+		"w" | "week" | "weeks" => 604_800_000_000_000,
+		_ => return None,
+	})
+}
+
+fn nanos_to_duration(nanos: u128) -> Result<Duration, String> {
+	u64::try_from(nanos)
+		.map(Duration::from_nanos)
+		.map_err(|_| too_long())
+}
+
+fn too_long() -> String {
+	"duration is too long".to_string()
+}
+
+/// A number of bytes, like `512`, `10MiB`, `1.5GB`, or `64k`.
+///
+/// Units are case-insensitive, and the trailing `B` is optional.  SI units (`k`, `M`, `G`, `T`,
+/// `P`) are powers of 1000, and IEC units (`Ki`, `Mi`, `Gi`, `Ti`, `Pi`) are powers of 1024, so
+/// `10MB` is 10,000,000 bytes, and `10MiB` is 10,485,760 bytes.  Fractions of a byte are
+/// dropped.
+///
+/// # Errors
+///
+/// If the value isn't a size, or is too big to fit in a `u64`.
+///
+pub fn byte_size(s: &str) -> Result<u64, String> {
+	let s = s.trim();
+	let (number, rest) = split_number(s);
+	if number.is_empty() {
+		return Err("expected a size, like 512, 64k, or 10MiB".to_string());
+	}
+	let (unit, rest) = split_unit(rest.trim_start());
+	if !rest.trim().is_empty() {
+		return Err(format!("unexpected {rest:?} after size"));
+	}
+
+	let unit = unit.to_ascii_lowercase();
+	let unit = unit.strip_suffix('b').unwrap_or(&unit);
+	let (base, prefix): (u128, &str) = match unit.strip_suffix('i') {
+		Some(prefix) if !prefix.is_empty() => (1024, prefix),
+		_ => (1000, unit),
+	};
+	let power = match prefix {
+		"" => 0,
+		"k" => 1,
+		"m" => 2,
+		"g" => 3,
+		"t" => 4,
+		"p" => 5,
+		_ => {
+			return Err(format!(
+				"unknown unit {unit:?} (expected something like k, M, G, Ki, Mi, or Gi)"
+			))
+		}
+	};
+	let per_unit = base.checked_pow(power).ok_or_else(too_big)?;
+
+	u64::try_from(scale(number, per_unit)?).map_err(|_| too_big())
+}
+
+fn too_big() -> String {
+	"size is too big".to_string()
+}
+
+/// A boolean, which can be given as `true`/`false`, `yes`/`no`, `y`/`n`, `on`/`off`, or `1`/`0`,
+/// in any case.
+///
+/// # Errors
+///
+/// If the value isn't any of those.
+///
+pub fn boolean(s: &str) -> Result<bool, String> {
+	match s.trim().to_ascii_lowercase().as_str() {
+		"true" | "t" | "yes" | "y" | "on" | "1" => Ok(true),
+		"false" | "f" | "no" | "n" | "off" | "0" => Ok(false),
+		_ => Err("expected true/false, yes/no, on/off, or 1/0".to_string()),
+	}
+}
+
+/// A socket address, where the port can be left off if it's `DEFAULT_PORT`.
+///
+/// IPv4 addresses look like `192.0.2.1` or `192.0.2.1:80`, and IPv6 addresses like `::1`,
+/// `[::1]`, or `[::1]:80`.  Host names aren't accepted, because looking them up when the config
+/// is read would tie the address down to whatever the name pointed to at the time.
+///
+/// # Errors
+///
+/// If the value isn't an IP address, with or without a port.
+///
+pub fn socket_addr<const DEFAULT_PORT: u16>(s: &str) -> Result<SocketAddr, String> {
+	let s = s.trim();
+
+	if let Ok(addr) = s.parse::<SocketAddr>() {
+		return Ok(addr);
+	}
+	if let Ok(ip) = s.parse::<IpAddr>() {
+		return Ok(SocketAddr::new(ip, DEFAULT_PORT));
+	}
+	if let Some(ip) = s
+		.strip_prefix('[')
+		.and_then(|s| s.strip_suffix(']'))
+		.and_then(|s| s.parse::<Ipv6Addr>().ok())
+	{
+		return Ok(SocketAddr::new(IpAddr::V6(ip), DEFAULT_PORT));
+	}
+
+	Err("expected an IP address, optionally followed by :<port>".to_string())
+}
+
+/// A URL, which has to have a host, so that `localhost:8080` (which is a perfectly valid URL,
+/// with a scheme of `localhost`) gets caught as the mistake it almost certainly is.
+///
+/// # Errors
+///
+/// If the value isn't a URL, or doesn't have a host.
+///
+#[cfg(feature = "url")]
+pub fn url(s: &str) -> Result<Url, String> {
+	let url = Url::parse(s.trim()).map_err(|e| e.to_string())?;
+
+	if url.has_host() {
+		Ok(url)
+	} else {
+		Err("URL must include a host, like https://example.com/".to_string())
+	}
+}
+
+/// Split the leading number (digits, with an optional fraction) off `s`.
+fn split_number(s: &str) -> (&str, &str) {
+	let end = s
+		.char_indices()
+		.find(|&(_, c)| !(c.is_ascii_digit() || c == '.'))
+		.map_or(s.len(), |(i, _)| i);
+	s.split_at(end)
+}
+
+/// Split the leading unit (letters) off `s`.
+fn split_unit(s: &str) -> (&str, &str) {
+	let end = s
+		.char_indices()
+		.find(|&(_, c)| !c.is_alphabetic())
+		.map_or(s.len(), |(i, _)| i);
+	s.split_at(end)
+}
+
+/// Multiply the decimal `number` by `per_unit`, dropping anything smaller than one.
+fn scale(number: &str, per_unit: u128) -> Result<u128, String> {
+	let invalid = || format!("{number:?} is not a number");
+
+	let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+	if whole.is_empty() && fraction.is_empty() {
+		return Err(invalid());
+	}
+	let whole: u128 = if whole.is_empty() {
+		0
+	} else {
+		whole.parse().map_err(|_| invalid())?
+	};
+	let fraction = fraction.trim_end_matches('0');
+
+	let mut total = whole.checked_mul(per_unit).ok_or_else(too_big_number)?;
+	if !fraction.is_empty() {
+		let digits = u32::try_from(fraction.len()).map_err(|_| invalid())?;
+		let fraction: u128 = fraction.parse().map_err(|_| invalid())?;
+		let part = fraction
+			.checked_mul(per_unit)
+			.and_then(|f| f.checked_div(10u128.checked_pow(digits)?))
+			.ok_or_else(too_big_number)?;
+		total = total.checked_add(part).ok_or_else(too_big_number)?;
+	}
+
+	Ok(total)
+}
+
+fn too_big_number() -> String {
+	"number is too big".to_string()
+}
@@ -0,0 +1,395 @@
+//! Config that can be read again while the service is running.
+//!
+
+use heck::AsShoutySnekCase;
+use secrecy::{zeroize::Zeroizing, ExposeSecret as _, SecretString};
+
+use std::{
+	collections::{hash_map::RandomState, BTreeMap, BTreeSet},
+	fmt::{self, Debug, Formatter},
+	fs,
+	hash::BuildHasher as _,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex, MutexGuard, PoisonError, RwLock, Weak,
+	},
+	thread,
+	time::{Duration, SystemTime},
+};
+
+use super::{load_from, publish_info, ConfigInfo, EnvVar, Service, Sources};
+use crate::{metric, Error};
+
+/// The name of the metric (less the service name) that counts reloads, by outcome.
+pub(crate) const RELOADS_METRIC: &str = "config_reloads";
+
+/// A config which can be read again while the service is running, and which the service can
+/// cheaply get the current version of whenever it needs it.
+///
+/// Use one by asking for a `Reloadable<MyConfig>` instead of a `MyConfig` in the function passed
+/// to [`Service::run`](crate::Service::run).  The config is reloaded either when
+/// [`reload`](Self::reload) is called (say, on `SIGHUP`), or whenever the config file changes, if
+/// [`watch`](Self::watch) has been called.
+///
+/// Changes to env vars are never picked up: the env vars that the config was first read from
+/// are kept, and reused on every reload, because the values of `sensitive` fields are removed
+/// from the environment once they have been read.  So it is only the config file (and any files
+/// that values are read from) that can change.  Only the env vars that the config actually uses
+/// are kept, and the values of those that hold secrets are kept in memory that is wiped when
+/// it's dropped.
+///
+/// ```rust
+/// use service_skeleton::{Reloadable, ServiceConfig};
+///
+/// #[derive(Clone, Debug, ServiceConfig)]
+/// struct Config {
+///     #[config(default_value = "World")]
+///     name: String,
+/// }
+///
+/// let cfg = Reloadable::<Config>::from_env_vars("hello", std::iter::empty()).unwrap();
+/// assert_eq!("World", cfg.get().name);
+/// assert!(!cfg.reload().unwrap());
+/// ```
+///
+pub struct Reloadable<CFG: Service> {
+	inner: Arc<Inner<CFG>>,
+}
+
+struct Inner<CFG> {
+	prefix: String,
+	current: RwLock<Arc<CFG>>,
+	state: Mutex<State>,
+	watching: AtomicBool,
+	/// For hashing the values of secrets, which are remembered only by their hash
+	hasher: RandomState,
+}
+
+/// What the config was last read from.
+struct State {
+	/// The env vars that the config uses, other than those holding secrets, to read the config
+	/// from again
+	vars: Vec<(String, String)>,
+	/// The env vars that hold secrets, which can't be read from the environment again, because
+	/// they're removed from it once they've been read
+	secret_vars: Vec<(String, SecretString)>,
+	/// The values of the vars that the config uses, to see what changed
+	values: BTreeMap<String, Seen>,
+}
+
+/// The value of a var, as remembered to see whether it changes.
+#[derive(Debug, PartialEq, Eq)]
+enum Seen {
+	Value(String),
+	/// The value of a secret, of which only the hash is kept
+	Secret(u64),
+}
+
+impl<CFG: Service> Clone for Reloadable<CFG> {
+	fn clone(&self) -> Self {
+		Self {
+			inner: Arc::clone(&self.inner),
+		}
+	}
+}
+
+impl<CFG: Service> Debug for Reloadable<CFG> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.fmt_redacted(f)
+	}
+}
+
+impl<CFG: Service> Reloadable<CFG> {
+	/// The config, as it was when it was last (successfully) read.
+	///
+	/// This is cheap enough to call on every request; hold on to the result for as long as you
+	/// need a consistent view of the config.
+	///
+	#[must_use]
+	pub fn get(&self) -> Arc<CFG> {
+		self.inner.current()
+	}
+
+	/// Read the config again, and start using the new version if it's valid.
+	///
+	/// Any fields that changed are logged, along with how (unless they're `sensitive` or
+	/// `encrypted`), and the `<service>_config_reloads` metric counts the reload by outcome:
+	/// `changed`, `unchanged`, or `failed`.  If the service publishes the `<service>_config_info`
+	/// metric, that's updated to describe the new config.
+	///
+	/// Returns whether any of the fields changed.  The new config is used even if none of them
+	/// did, because the contents of files that values are read from might have.
+	///
+	/// # Errors
+	///
+	/// If the config can't be read, or isn't valid; the config in use is left as it was.
+	///
+	pub fn reload(&self) -> Result<bool, Error> {
+		self.inner.reload()
+	}
+
+	/// Reload the config whenever the file named in `<PREFIX>_CONFIG_FILE` changes, checking
+	/// every `interval`.
+	///
+	/// Problems with the new config are logged, and the old config stays in use.  The checking
+	/// stops once every copy of this `Reloadable` has been dropped.  Calling this more than once
+	/// (as will happen if the service is restarted after a panic) does nothing.
+	///
+	pub fn watch(&self, interval: Duration)
+	where
+		CFG: Send + Sync + 'static,
+	{
+		if self.inner.watching.swap(true, Ordering::SeqCst) {
+			return;
+		}
+
+		let var = format!("{}_CONFIG_FILE", AsShoutySnekCase(&self.inner.prefix));
+		let path = self
+			.inner
+			.state()
+			.vars
+			.iter()
+			.find(|(name, _)| *name == var)
+			.map(|(_, path)| PathBuf::from(path));
+		let Some(path) = path else {
+			tracing::info!("Not watching for config changes: {var} is not set");
+			return;
+		};
+
+		let inner = Arc::downgrade(&self.inner);
+		thread::spawn(move || watch_file(&inner, &path, interval));
+	}
+}
+
+impl<CFG: Service> Inner<CFG> {
+	fn current(&self) -> Arc<CFG> {
+		Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+	}
+
+	fn state(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn reload(&self) -> Result<bool, Error> {
+		// Held throughout, so that concurrent reloads don't trip over each other
+		let mut state = self.state();
+
+		// The copies of the secrets end up in the Sources, which wipes them when it's dropped
+		let vars = state.vars.iter().cloned().chain(
+			state
+				.secret_vars
+				.iter()
+				.map(|(var, value)| (var.clone(), value.expose_secret().to_string())),
+		);
+		// This can be running on the watcher's thread, so the environment mustn't be touched
+		let loaded = Sources::new(&self.prefix, vars)
+			.and_then(|sources| load_from::<CFG>(&self.prefix, sources.without_purging()));
+		let (cfg, sources) = match loaded {
+			Ok(loaded) => loaded,
+			Err(e) => {
+				count_reload("failed");
+				return Err(e);
+			}
+		};
+
+		let docs = self.docs();
+		let values = remember(&docs, &self.hasher, sources.known_values());
+		let changed = log_changes(&docs, &state.values, &values);
+		state.values = values;
+
+		publish_info(&cfg);
+		*self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(cfg);
+
+		count_reload(if changed { "changed" } else { "unchanged" });
+		Ok(changed)
+	}
+
+	fn docs(&self) -> Vec<EnvVar> {
+		CFG::fields(&AsShoutySnekCase(&self.prefix).to_string())
+	}
+}
+
+/// Log every field whose value differs between `old` and `new` (along with how, unless it's a
+/// secret), and say whether there were any.
+fn log_changes(
+	docs: &[EnvVar],
+	old: &BTreeMap<String, Seen>,
+	new: &BTreeMap<String, Seen>,
+) -> bool {
+	// The changes to each field (or var, if it isn't a field's), as the fields of lists and
+	// maps are read from more than one var
+	let mut changes: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+	let vars: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+	for var in vars {
+		let (old_value, new_value) = (old.get(var), new.get(var));
+		if old_value == new_value {
+			continue;
+		}
+
+		let field = field_for(docs, var).unwrap_or(var.as_str());
+		let change = match (old_value, new_value) {
+			(Some(Seen::Value(old_value)), Some(Seen::Value(new_value))) => {
+				Some(format!("{var} changed from {old_value:?} to {new_value:?}"))
+			}
+			(None, Some(Seen::Value(new_value))) => Some(format!("{var} set to {new_value:?}")),
+			(Some(Seen::Value(_)), None) => Some(format!("{var} unset")),
+			(Some(Seen::Secret(_)), _) | (_, Some(Seen::Secret(_))) | (None, None) => None,
+		};
+		changes.entry(field).or_default().extend(change);
+	}
+
+	for (field, field_changes) in &changes {
+		if field_changes.is_empty() {
+			tracing::info!("Config field {field} changed (value redacted)");
+		} else {
+			tracing::info!("Config field {field} changed: {}", field_changes.join(", "));
+		}
+	}
+
+	!changes.is_empty()
+}
+
+impl<CFG: Service> Service for Reloadable<CFG> {
+	fn from_sources(prefix: &str, sources: &mut Sources) -> Result<Self, Error> {
+		let cfg = CFG::from_sources(prefix, sources)?;
+
+		let docs = CFG::fields(&AsShoutySnekCase(prefix).to_string());
+		let hasher = RandomState::new();
+		let (secret_vars, vars): (Vec<_>, Vec<_>) = sources
+			.env_snapshot(prefix)
+			.into_iter()
+			.partition(|(var, _)| is_secret(&docs, var));
+		let vars = vars
+			.into_iter()
+			.map(|(var, value)| (var, value.to_string()))
+			.collect();
+		let secret_vars = secret_vars
+			.into_iter()
+			.map(|(var, value)| (var, SecretString::from(value.as_str())))
+			.collect();
+
+		Ok(Self {
+			inner: Arc::new(Inner {
+				prefix: prefix.to_string(),
+				current: RwLock::new(Arc::new(cfg)),
+				state: Mutex::new(State {
+					vars,
+					secret_vars,
+					values: remember(&docs, &hasher, sources.known_values()),
+				}),
+				watching: AtomicBool::new(false),
+				hasher,
+			}),
+		})
+	}
+
+	fn cli_flags() -> bool {
+		CFG::cli_flags()
+	}
+
+	fn deny_unknown_vars() -> bool {
+		CFG::deny_unknown_vars()
+	}
+
+	fn reloadable() -> bool {
+		true
+	}
+
+	fn fields(prefix: &str) -> Vec<EnvVar> {
+		CFG::fields(prefix)
+	}
+
+	fn config_info(&self, path: &str, info: &mut ConfigInfo) {
+		self.get().config_info(path, info);
+	}
+
+	fn fmt_redacted(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Reloadable")
+			.field(&self.get().redacted())
+			.finish()
+	}
+}
+
+/// The doc for the var that `var` is (part of the value of), if any; the longest match wins, so
+/// that `<PREFIX>_LABELS_EXTRA` belongs to a `labels_extra` field, rather than a `labels` map.
+fn doc_for<'a>(docs: &'a [EnvVar], var: &str) -> Option<&'a EnvVar> {
+	docs.iter()
+		.filter(|doc| {
+			var.strip_prefix(doc.name.as_str())
+				.is_some_and(|rest| rest.is_empty() || rest.starts_with('_'))
+		})
+		.max_by_key(|doc| doc.name.len())
+}
+
+/// Whether `var` holds (part of) the value of a `sensitive` or `encrypted` field.
+fn is_secret(docs: &[EnvVar], var: &str) -> bool {
+	doc_for(docs, var).is_some_and(|doc| doc.sensitive || doc.encrypted)
+}
+
+/// The path of the field that `var` holds (part of) the value of, if it's a field's.
+fn field_for<'a>(docs: &'a [EnvVar], var: &str) -> Option<&'a str> {
+	doc_for(docs, var).and_then(|doc| doc.field.as_deref())
+}
+
+/// The values of the vars that the config uses, as they're worth remembering: secrets only by
+/// their hash.
+fn remember(
+	docs: &[EnvVar],
+	hasher: &RandomState,
+	values: BTreeMap<String, Zeroizing<String>>,
+) -> BTreeMap<String, Seen> {
+	values
+		.into_iter()
+		.map(|(var, value)| {
+			let seen = if is_secret(docs, &var) {
+				Seen::Secret(hasher.hash_one(value.as_str()))
+			} else {
+				Seen::Value(value.to_string())
+			};
+			(var, seen)
+		})
+		.collect()
+}
+
+/// Count a reload in the reloads metric, if the service has one; configs that are reloaded
+/// outside of a running service (like in tests) won't.
+fn count_reload(outcome: &'static str) {
+	if metric::is_registered(RELOADS_METRIC) {
+		metric::counter(RELOADS_METRIC, &vec![("outcome", outcome)], |c| {
+			c.inc();
+		});
+	}
+}
+
+/// Reload the config whenever the file at `path` changes, for as long as the config is around.
+fn watch_file<CFG: Service>(inner: &Weak<Inner<CFG>>, path: &Path, interval: Duration) {
+	let mut last_seen = file_version(path);
+
+	loop {
+		thread::sleep(interval);
+
+		let Some(inner) = inner.upgrade() else {
+			return;
+		};
+
+		let version = file_version(path);
+		if version == last_seen {
+			continue;
+		}
+		last_seen = version;
+
+		tracing::info!("Config file {} changed; reloading", path.display());
+		if let Err(e) = inner.reload() {
+			for problem in e.problems() {
+				tracing::error!("Failed to reload config: {problem}");
+			}
+		}
+	}
+}
+
+/// Something that changes whenever the file is written to.
+fn file_version(path: &Path) -> Option<(SystemTime, u64)> {
+	let metadata = fs::metadata(path).ok()?;
+	Some((metadata.modified().ok()?, metadata.len()))
+}
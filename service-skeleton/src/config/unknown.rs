@@ -0,0 +1,77 @@
+//! Spotting env vars that look like they're meant for the service, but that nothing reads.
+//!
+
+use heck::AsShoutySnekCase;
+
+use super::{docs::SKELETON_VARS, Sources};
+use crate::Error;
+
+/// How different a known var's name can be from an unknown var's name, and still be suggested as
+/// the one that was probably meant.
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// Find all the env vars that start with `<PREFIX>_`, but which weren't looked at while reading
+/// the config, and aren't one of the skeleton's own settings.
+///
+/// Each one comes back as an [`Error::ConfigVarUnknown`], with a suggestion of the known var
+/// with the closest name, if there's one that's close enough.
+///
+pub(crate) fn unknown_vars(sources: &Sources, prefix: &str) -> Vec<Error> {
+	let prefix = format!("{}_", AsShoutySnekCase(prefix));
+
+	let mut candidates: Vec<String> = sources
+		.known_vars()
+		.into_iter()
+		.filter(|var| var.starts_with(&prefix))
+		.chain(
+			SKELETON_VARS
+				.iter()
+				.map(|(var, _, _)| format!("{prefix}{var}")),
+		)
+		.collect();
+	candidates.sort();
+	candidates.dedup();
+
+	let mut unknown: Vec<&str> = sources
+		.env_vars()
+		.filter(|var| var.starts_with(&prefix) && !sources.is_known(var))
+		.filter(|var| !candidates.iter().any(|c| c == *var))
+		.collect();
+	unknown.sort_unstable();
+
+	unknown
+		.into_iter()
+		.map(|var| {
+			let suggestion = candidates
+				.iter()
+				.map(|c| (edit_distance(var, c), c))
+				.filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+				.min_by_key(|(distance, _)| *distance)
+				.map(|(_, c)| c.as_str());
+			Error::config_var_unknown(var, suggestion)
+		})
+		.collect()
+}
+
+/// The Levenshtein distance between `a` and `b`; that is, the number of characters that need to
+/// be inserted, removed, or changed to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+	for (i, ca) in a.chars().enumerate() {
+		let mut row = vec![i.saturating_add(1)];
+		for (cb, (diagonal, above)) in b.iter().zip(prev.iter().zip(prev.iter().skip(1))) {
+			let left = row.last().copied().unwrap_or_default();
+			let substitute = diagonal.saturating_add(usize::from(ca != *cb));
+			row.push(
+				substitute
+					.min(above.saturating_add(1))
+					.min(left.saturating_add(1)),
+			);
+		}
+		prev = row;
+	}
+
+	prev.last().copied().unwrap_or_default()
+}
@@ -0,0 +1,113 @@
+//! The checks behind the derive macro's validation attributes.
+//!
+//! Each of these returns an [`Error::ConfigValueInvalid`] naming `var` and the rule that the
+//! value broke, if the value doesn't pass muster.  The value itself is left out of the error,
+//! because it might be a secret.
+//!
+
+use regex::Regex;
+use std::fmt::Display;
+
+use crate::Error;
+
+/// `#[config(range(min = ..., max = ...))]`; either end of the range can be left open.
+///
+/// # Errors
+///
+/// If the value is outside the range.
+///
+pub fn validate_range<T: PartialOrd + Display>(
+	var: &str,
+	value: &T,
+	min: Option<T>,
+	max: Option<T>,
+) -> Result<(), Error> {
+	let too_small = min.as_ref().is_some_and(|min| value < min);
+	let too_big = max.as_ref().is_some_and(|max| value > max);
+
+	let rule = match (min, max) {
+		_ if !(too_small || too_big) => return Ok(()),
+		(Some(min), Some(max)) => format!("must be between {min} and {max}"),
+		(Some(min), None) => format!("must be at least {min}"),
+		(None, Some(max)) => format!("must be at most {max}"),
+		// Nothing can be outside an unbounded range
+		(None, None) => return Ok(()),
+	};
+	Err(Error::config_value_invalid(var, rule))
+}
+
+/// `#[config(non_empty)]`
+///
+/// # Errors
+///
+/// If the value is empty.
+///
+pub fn validate_non_empty(var: &str, is_empty: bool) -> Result<(), Error> {
+	if is_empty {
+		Err(Error::config_value_invalid(var, "must not be empty"))
+	} else {
+		Ok(())
+	}
+}
+
+/// `#[config(regex = "...")]`; the pattern has to match somewhere in the value, so anchor it
+/// if it should match the whole thing.
+///
+/// # Errors
+///
+/// If the value doesn't match the pattern.
+///
+pub fn validate_regex(var: &str, value: &str, pattern: &str) -> Result<(), Error> {
+	// The derive macro has already made sure that the pattern is valid
+	let matches = Regex::new(pattern).is_ok_and(|re| re.is_match(value));
+
+	if matches {
+		Ok(())
+	} else {
+		Err(Error::config_value_invalid(
+			var,
+			format!("must match /{pattern}/"),
+		))
+	}
+}
+
+/// `#[config(one_of(...))]`
+///
+/// # Errors
+///
+/// If the value isn't one of the allowed values.
+///
+pub fn validate_one_of<T: PartialEq<A>, A: Display>(
+	var: &str,
+	value: &T,
+	allowed: &[A],
+) -> Result<(), Error> {
+	if allowed.iter().any(|a| value == a) {
+		return Ok(());
+	}
+
+	let allowed = allowed
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>()
+		.join(", ");
+	Err(Error::config_value_invalid(
+		var,
+		format!("must be one of {allowed}"),
+	))
+}
+
+/// `#[config(validate = some_fn)]`, on a field or a whole struct, where `some_fn` returns an
+/// error explaining what's wrong with the value.
+///
+/// # Errors
+///
+/// If the validation function does.
+///
+pub fn validate_with<T: ?Sized, E: Display>(
+	var: &str,
+	value: &T,
+	validator: impl FnOnce(&T) -> Result<(), E>,
+) -> Result<(), Error> {
+	validator(value).map_err(|e| Error::config_value_invalid(var, e))
+}
@@ -14,6 +14,49 @@ pub enum Error {
 	#[error("failed to parse value of {var}: {cause}")]
 	ConfigValueParse { var: String, cause: String },
 
+	#[error("invalid value for {var}: {rule}")]
+	ConfigValueInvalid { var: String, rule: String },
+
+	#[error("both {var} and {file_var} are set; only one of them can be used")]
+	ConfigValueConflict { var: String, file_var: String },
+
+	#[error("failed to read value of {var} from {file}")]
+	ConfigValueFileRead {
+		var: String,
+		file: String,
+		#[source]
+		cause: std::io::Error,
+	},
+
+	#[error("failed to read config file {file}")]
+	ConfigFileRead {
+		file: String,
+		#[source]
+		cause: std::io::Error,
+	},
+
+	#[error("failed to parse config file {file}: {cause}")]
+	ConfigFileParse { file: String, cause: String },
+
+	#[error("failed to parse value of {key} in config file {file}: {cause}")]
+	ConfigFileValueParse {
+		file: String,
+		key: String,
+		cause: String,
+	},
+
+	#[error("unknown config variable {var}{}", did_you_mean(.suggestion.as_deref()))]
+	ConfigVarUnknown {
+		var: String,
+		suggestion: Option<String>,
+	},
+
+	#[error("invalid command-line option {flag}: {cause}")]
+	ConfigFlag { flag: String, cause: String },
+
+	#[error("{} problems with config: {}", .errors.len(), join(.errors))]
+	Config { errors: Vec<Error> },
+
 	#[error("no metric named {name}")]
 	NoSuchMetric { name: String },
 
@@ -55,6 +98,64 @@ impl Error {
 		}
 	}
 
+	#[must_use]
+	pub fn config_value_invalid(var: &str, rule: impl Display) -> Error {
+		Error::ConfigValueInvalid {
+			var: var.to_string(),
+			rule: rule.to_string(),
+		}
+	}
+
+	#[must_use]
+	pub fn config_value_conflict(var: &str, file_var: &str) -> Error {
+		Error::ConfigValueConflict {
+			var: var.to_string(),
+			file_var: file_var.to_string(),
+		}
+	}
+
+	#[must_use]
+	pub fn config_value_file_read(
+		var: &str,
+		file: impl Into<String>,
+		cause: std::io::Error,
+	) -> Error {
+		Error::ConfigValueFileRead {
+			var: var.to_string(),
+			file: file.into(),
+			cause,
+		}
+	}
+
+	#[must_use]
+	pub fn config_file_read(file: impl Into<String>, cause: std::io::Error) -> Error {
+		Error::ConfigFileRead {
+			file: file.into(),
+			cause,
+		}
+	}
+
+	#[must_use]
+	pub fn config_file_parse(file: impl Into<String>, cause: impl Display) -> Error {
+		Error::ConfigFileParse {
+			file: file.into(),
+			cause: cause.to_string(),
+		}
+	}
+
+	#[must_use]
+	pub fn config_file_value_parse(
+		file: impl Into<String>,
+		key: impl Into<String>,
+		cause: impl Display,
+	) -> Error {
+		Error::ConfigFileValueParse {
+			file: file.into(),
+			key: key.into(),
+			cause: cause.to_string(),
+		}
+	}
+
 	#[must_use]
 	pub fn key_read(file: impl Into<String>, cause: std::io::Error) -> Error {
 		Error::KeyRead {
@@ -63,6 +164,58 @@ impl Error {
 		}
 	}
 
+	#[must_use]
+	pub fn config_var_unknown(var: &str, suggestion: Option<&str>) -> Error {
+		Error::ConfigVarUnknown {
+			var: var.to_string(),
+			suggestion: suggestion.map(ToString::to_string),
+		}
+	}
+
+	#[must_use]
+	pub fn config_flag(flag: &str, cause: impl Display) -> Error {
+		Error::ConfigFlag {
+			flag: flag.to_string(),
+			cause: cause.to_string(),
+		}
+	}
+
+	/// Roll up all the problems found while reading a config into one error.
+	///
+	/// Any of `errors` that are themselves a collection of problems (from a nested config) are
+	/// unpacked, and a single problem is returned as-is, rather than as a list of one.
+	#[must_use]
+	pub fn config_errors(errors: Vec<Error>) -> Error {
+		let mut errors: Vec<Error> = errors
+			.into_iter()
+			.flat_map(|e| {
+				if let Error::Config { errors } = e {
+					errors
+				} else {
+					vec![e]
+				}
+			})
+			.collect();
+
+		if errors.len() == 1 {
+			#[allow(clippy::unwrap_used)] // We just checked there's one in there
+			errors.pop().unwrap()
+		} else {
+			Error::Config { errors }
+		}
+	}
+
+	/// The individual problems that make up this error; just the error itself, unless it is a
+	/// collection of config problems.
+	#[must_use]
+	pub fn problems(&self) -> Vec<&Error> {
+		if let Error::Config { errors } = self {
+			errors.iter().collect()
+		} else {
+			vec![self]
+		}
+	}
+
 	#[must_use]
 	pub fn no_metric(name: &str) -> Error {
 		Error::NoSuchMetric {
@@ -87,3 +240,15 @@ impl Error {
 		Error::MetricsServerStart { port, cause }
 	}
 }
+
+fn join(errors: &[Error]) -> String {
+	errors
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>()
+		.join("; ")
+}
+
+fn did_you_mean(suggestion: Option<&str>) -> String {
+	suggestion.map_or_else(String::new, |s| format!("; did you mean {s}?"))
+}
@@ -4,17 +4,23 @@ pub struct ReadmeDoctests;
 
 #[doc(hidden)]
 pub mod config;
-pub use config::Service as ServiceConfig;
+pub use config::{EnvDocs, EnvVar, EnvVarKind, Redacted, Reloadable, Service as ServiceConfig};
 
 mod error;
 pub use error::Error;
 
+pub mod logging;
+
 pub mod metric;
 
 mod service;
 pub use service::{service, Service};
 
+pub mod testing;
+
 #[doc(hidden)]
 pub use heck;
+#[doc(hidden)]
+pub use secrecy;
 
 pub use service_skeleton_derive::ServiceConfig;
@@ -0,0 +1,58 @@
+use prometheus_client::{
+	metrics::{counter::Counter, family::Family},
+	registry::Registry,
+};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+/// A tracing layer which counts the events that get logged, by level (and, optionally, target).
+///
+/// The counts are available as a metric, once registered with
+/// [`register_metrics`](Self::register_metrics).  Only events which actually get logged are
+/// counted; events which are filtered out, or dropped by rate limiting, are not.
+///
+#[derive(Debug, Default)]
+pub struct EventCounterLayer {
+	by_target: bool,
+	// Labels are a Vec so the target label can be left off entirely when not wanted
+	events: Family<Vec<(&'static str, String)>, Counter>,
+}
+
+impl EventCounterLayer {
+	/// Create a new event counting layer, which counts events by level.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Count events by target, as well as level.
+	///
+	/// Be wary of using this if your service (or its dependencies) log under a great many
+	/// different targets, as each one becomes a separate time series.
+	#[must_use]
+	pub fn with_targets(self, by_target: bool) -> Self {
+		Self { by_target, ..self }
+	}
+
+	/// Add the metric that counts events to a registry.
+	pub fn register_metrics(&self, registry: &mut Registry) {
+		registry.register(
+			"log_events",
+			"Number of log events emitted, by level",
+			self.events.clone(),
+		);
+	}
+}
+
+impl<S: Subscriber> Layer<S> for EventCounterLayer {
+	fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+		let metadata = event.metadata();
+
+		let mut labels = vec![("level", metadata.level().as_str().to_ascii_lowercase())];
+		if self.by_target {
+			labels.push(("target", metadata.target().to_string()));
+		}
+
+		self.events.get_or_create(&labels).inc();
+	}
+}
@@ -0,0 +1,101 @@
+//! Odds and ends for getting field values out of events and spans, shared between the various
+//! log sinks.
+//!
+
+use tracing::{
+	field::{Field, Visit},
+	span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan};
+
+use std::fmt::Debug;
+
+/// The values of all the fields attached to an event or span, rendered as strings.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FieldValues(pub(crate) Vec<(&'static str, String)>);
+
+impl FieldValues {
+	pub(crate) fn from_event(event: &Event<'_>) -> Self {
+		let mut values = Self::default();
+		event.record(&mut values);
+		values
+	}
+
+	/// Remove the `message` field from the set of values, and return it (if there was one).
+	pub(crate) fn take_message(&mut self) -> Option<String> {
+		let idx = self.0.iter().position(|(name, _)| *name == "message")?;
+		Some(self.0.remove(idx).1)
+	}
+}
+
+impl Visit for FieldValues {
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.0.push((field.name(), value.to_string()));
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+		#[allow(clippy::use_debug)] // Debug is all we've got to go on here
+		self.0.push((field.name(), format!("{value:?}")));
+	}
+}
+
+/// The fields recorded against a span, kept in the span's extensions so they're available when
+/// an event is emitted inside the span.
+#[derive(Debug)]
+pub(crate) struct SpanFields(pub(crate) FieldValues);
+
+pub(crate) fn record_new_span<S>(attrs: &span::Attributes<'_>, id: &span::Id, ctx: &Context<'_, S>)
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	let Some(span) = ctx.span(id) else {
+		return;
+	};
+
+	let mut values = FieldValues::default();
+	attrs.record(&mut values);
+	span.extensions_mut().replace(SpanFields(values));
+}
+
+pub(crate) fn record_span_values<S>(id: &span::Id, record: &span::Record<'_>, ctx: &Context<'_, S>)
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	let Some(span) = ctx.span(id) else {
+		return;
+	};
+
+	let mut extensions = span.extensions_mut();
+	if let Some(SpanFields(values)) = extensions.get_mut::<SpanFields>() {
+		record.record(values);
+	} else {
+		let mut values = FieldValues::default();
+		record.record(&mut values);
+		extensions.insert(SpanFields(values));
+	}
+}
+
+/// Get the name and recorded fields of every span the event is in, from the outermost inwards.
+pub(crate) fn event_spans<S>(
+	event: &Event<'_>,
+	ctx: &Context<'_, S>,
+) -> Vec<(&'static str, FieldValues)>
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	ctx.event_scope(event)
+		.map(|scope| {
+			scope
+				.from_root()
+				.map(|span| {
+					let values = span
+						.extensions()
+						.get::<SpanFields>()
+						.map(|SpanFields(v)| v.clone())
+						.unwrap_or_default();
+					(span.name(), values)
+				})
+				.collect()
+		})
+		.unwrap_or_default()
+}
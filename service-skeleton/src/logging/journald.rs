@@ -0,0 +1,172 @@
+use tracing::{span, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use std::{io, os::unix::net::UnixDatagram, path::Path};
+
+use super::fields::{event_spans, record_new_span, record_span_values, FieldValues};
+
+/// A tracing layer which sends events to the systemd journal, using the journal's [native
+/// protocol](https://systemd.io/JOURNAL_NATIVE_PROTOCOL/).
+///
+/// Each event is sent as a single datagram containing the usual journal fields (`MESSAGE`,
+/// `PRIORITY`, `SYSLOG_IDENTIFIER`, `CODE_FILE`, `CODE_LINE`, and friends), along with all of the
+/// event's own fields (upper-cased, as the journal requires).  The fields of the spans that the
+/// event occurred in are sent as `S<depth>_NAME` and `S<depth>_<FIELD>`, where the outermost span
+/// has a depth of zero.
+///
+/// An event field whose name would clash with one of those, or with one of the journal's other
+/// well-known fields, gets an `F_` prefix, so that `info!(priority = 7, ...)` is sent as
+/// `F_PRIORITY=7`, rather than passing itself off as the event's priority.
+///
+#[derive(Debug)]
+pub struct JournaldLayer {
+	identifier: String,
+	socket: UnixDatagram,
+}
+
+impl JournaldLayer {
+	/// Create a new layer that sends events to the journal listening on `socket`, identifying
+	/// itself as `identifier`.
+	///
+	/// The usual location for the journal's socket is `/run/systemd/journal/socket`.
+	pub fn new(identifier: impl Into<String>, socket: impl AsRef<Path>) -> io::Result<Self> {
+		let sock = UnixDatagram::unbound()?;
+		sock.connect(socket)?;
+
+		Ok(Self {
+			identifier: identifier.into(),
+			socket: sock,
+		})
+	}
+}
+
+impl<S> Layer<S> for JournaldLayer
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+		record_new_span(attrs, id, &ctx);
+	}
+
+	fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+		record_span_values(id, values, &ctx);
+	}
+
+	fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+		let meta = event.metadata();
+		let mut values = FieldValues::from_event(event);
+		let mut buf: Vec<u8> = Vec::new();
+
+		put_field(&mut buf, "PRIORITY", priority(*meta.level()));
+		put_field(&mut buf, "SYSLOG_IDENTIFIER", &self.identifier);
+		put_field(
+			&mut buf,
+			"MESSAGE",
+			&values.take_message().unwrap_or_default(),
+		);
+		put_field(&mut buf, "TARGET", meta.target());
+		if let Some(file) = meta.file() {
+			put_field(&mut buf, "CODE_FILE", file);
+		}
+		if let Some(line) = meta.line() {
+			put_field(&mut buf, "CODE_LINE", &line.to_string());
+		}
+		if let Some(module) = meta.module_path() {
+			put_field(&mut buf, "CODE_MODULE", module);
+		}
+
+		for (depth, (name, span_values)) in event_spans(event, &ctx).into_iter().enumerate() {
+			put_field(&mut buf, &format!("S{depth}_NAME"), name);
+			for (field, value) in span_values.0 {
+				put_field(&mut buf, &format!("S{depth}_{}", field_name(field)), &value);
+			}
+		}
+
+		for (field, value) in values.0 {
+			put_field(&mut buf, &event_field_name(field), &value);
+		}
+
+		// There's nowhere sensible to report a failure to log, so we don't
+		self.socket.send(&buf).ok();
+	}
+}
+
+fn priority(level: Level) -> &'static str {
+	match level {
+		Level::ERROR => "3",
+		Level::WARN => "4",
+		Level::INFO => "6",
+		Level::DEBUG | Level::TRACE => "7",
+	}
+}
+
+/// Turn an arbitrary field name into something the journal will accept: upper-case ASCII letters,
+/// digits, and underscores, starting with a letter, and no more than 64 characters long.
+fn field_name(name: &str) -> String {
+	let mut s: String = name
+		.chars()
+		.map(|c| {
+			if c.is_ascii_alphanumeric() {
+				c.to_ascii_uppercase()
+			} else {
+				'_'
+			}
+		})
+		.collect();
+
+	if !s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+		s.insert_str(0, "F_");
+	}
+	s.truncate(64);
+	s
+}
+
+/// The journal fields that mean something to the journal (or to us), which an event's own fields
+/// mustn't be sent as; the `CODE_` and `SYSLOG_` families are reserved wholesale.
+const RESERVED_FIELDS: &[&str] = &[
+	"DOCUMENTATION",
+	"ERRNO",
+	"INVOCATION_ID",
+	"MESSAGE",
+	"MESSAGE_ID",
+	"PRIORITY",
+	"TARGET",
+	"TID",
+	"UNIT",
+	"USER_INVOCATION_ID",
+	"USER_UNIT",
+];
+
+/// The journal field name for one of an event's own fields, which is prefixed with `F_` if it
+/// would otherwise be taken for one of the fields that we (or the journal) give meaning to, or for
+/// one of the span fields.  Names that already start with `F_` get another one, so that a field
+/// called `f_priority` can't be confused with one called `priority`.
+fn event_field_name(name: &str) -> String {
+	let mut s = field_name(name);
+
+	let is_span_field = s
+		.strip_prefix('S')
+		.and_then(|rest| rest.split_once('_'))
+		.is_some_and(|(depth, _)| !depth.is_empty() && depth.bytes().all(|b| b.is_ascii_digit()));
+	if RESERVED_FIELDS.contains(&s.as_str())
+		|| ["CODE_", "SYSLOG_", "F_"].iter().any(|p| s.starts_with(p))
+		|| is_span_field
+	{
+		s.insert_str(0, "F_");
+		s.truncate(64);
+	}
+	s
+}
+
+fn put_field(buf: &mut Vec<u8>, name: &str, value: &str) {
+	buf.extend_from_slice(name.as_bytes());
+	if value.contains('\n') {
+		// Multi-line values have to be sent as a length-prefixed binary blob
+		buf.push(b'\n');
+		buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+	} else {
+		buf.push(b'=');
+	}
+	buf.extend_from_slice(value.as_bytes());
+	buf.push(b'\n');
+}
@@ -0,0 +1,335 @@
+//! Where log messages go, and how they get there.
+//!
+
+use prometheus_client::registry::Registry as MetricsRegistry;
+use tracing_subscriber::{
+	filter::{Directive, FilterExt as _, LevelFilter},
+	layer::{Filter, SubscriberExt as _},
+	registry::{LookupSpan, Registry},
+	EnvFilter, Layer,
+};
+
+use std::{
+	env::{self, VarError},
+	fmt::{self, Display, Formatter},
+	path::PathBuf,
+	str::FromStr,
+};
+
+pub(crate) mod fields;
+
+mod event_counter;
+pub use event_counter::EventCounterLayer;
+
+mod rate_limit;
+pub use rate_limit::RateLimitLayer;
+
+#[cfg(unix)]
+mod journald;
+#[cfg(unix)]
+pub use journald::JournaldLayer;
+
+#[cfg(unix)]
+mod syslog;
+#[cfg(unix)]
+pub use syslog::SyslogLayer;
+
+#[cfg(feature = "opentelemetry")]
+mod otel;
+
+/// The default location of the systemd journal's native protocol socket.
+pub const DEFAULT_JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// The default location of the local syslog daemon's socket.
+pub const DEFAULT_SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Where log messages should be sent.
+///
+/// The sink can be set with [`Service::log_sink`](crate::Service::log_sink), and overridden at
+/// runtime by setting the `<PREFIX>_LOG_SINK` environment variable to one of `stderr`,
+/// `journald`, or `syslog`.  The `journald` and `syslog` sinks can also be given the path to the
+/// socket to send to, like `syslog:/var/run/syslog`.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LogSink {
+	/// Pretty, hierarchical, human-readable log output to stderr.  This is the default.
+	#[default]
+	Stderr,
+	/// Structured log entries sent to the systemd journal socket at the given path.
+	Journald(PathBuf),
+	/// RFC 5424-formatted log entries sent to the syslog socket at the given path.
+	Syslog(PathBuf),
+}
+
+impl LogSink {
+	/// Send logs to the systemd journal, via the usual socket.
+	#[must_use]
+	pub fn journald() -> Self {
+		Self::Journald(DEFAULT_JOURNALD_SOCKET.into())
+	}
+
+	/// Send logs to the local syslog daemon, via the usual socket.
+	#[must_use]
+	pub fn syslog() -> Self {
+		Self::Syslog(DEFAULT_SYSLOG_SOCKET.into())
+	}
+
+	#[cfg(unix)]
+	fn layer(&self, identifier: &str) -> std::io::Result<Box<dyn Layer<Registry> + Send + Sync>> {
+		Ok(match self {
+			Self::Stderr => stderr_layer(),
+			Self::Journald(socket) => JournaldLayer::new(identifier, socket)?.boxed(),
+			Self::Syslog(socket) => SyslogLayer::new(identifier, socket)?.boxed(),
+		})
+	}
+
+	#[cfg(not(unix))]
+	fn layer(&self, _identifier: &str) -> std::io::Result<Box<dyn Layer<Registry> + Send + Sync>> {
+		match self {
+			Self::Stderr => Ok(stderr_layer()),
+			Self::Journald(_) | Self::Syslog(_) => Err(std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				"only available on Unix-like systems",
+			)),
+		}
+	}
+}
+
+impl Display for LogSink {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Stderr => f.write_str("stderr"),
+			Self::Journald(socket) => write!(f, "journald:{}", socket.display()),
+			Self::Syslog(socket) => write!(f, "syslog:{}", socket.display()),
+		}
+	}
+}
+
+impl FromStr for LogSink {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (kind, socket) = match s.split_once(':') {
+			Some((k, path)) => (k, Some(PathBuf::from(path))),
+			None => (s, None),
+		};
+
+		match (kind, socket) {
+			("stderr", None) => Ok(Self::Stderr),
+			("journald", None) => Ok(Self::journald()),
+			("journald", Some(path)) => Ok(Self::Journald(path)),
+			("syslog", None) => Ok(Self::syslog()),
+			("syslog", Some(path)) => Ok(Self::Syslog(path)),
+			_ => Err(format!(
+				"unknown log sink '{s}' (expected one of stderr, journald[:<socket>], or syslog[:<socket>])"
+			)),
+		}
+	}
+}
+
+fn stderr_layer() -> Box<dyn Layer<Registry> + Send + Sync> {
+	tracing_tree::HierarchicalLayer::default()
+		.with_writer(tracing_subscriber::fmt::TestWriter::new())
+		.with_indent_lines(true)
+		.with_indent_amount(2)
+		.with_targets(true)
+		.boxed()
+}
+
+/// The logging-related knobs that can be twiddled via the [`Service`](crate::Service) builder.
+#[derive(Clone, Debug)]
+pub(crate) struct Settings {
+	pub(crate) sink: LogSink,
+	pub(crate) default_level: LevelFilter,
+	/// The burst size and per-second rate for log rate limiting, if it's turned on
+	pub(crate) rate_limit: Option<(u32, u32)>,
+	/// Whether the log event counter metric should have a `target` label
+	pub(crate) count_by_target: bool,
+	/// What proportion of traces to export, if not the OpenTelemetry default
+	#[cfg(feature = "opentelemetry")]
+	pub(crate) trace_sample_ratio: Option<f64>,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			sink: LogSink::default(),
+			default_level: LevelFilter::INFO,
+			rate_limit: Some((100, 10)),
+			count_by_target: false,
+			#[cfg(feature = "opentelemetry")]
+			trace_sample_ratio: None,
+		}
+	}
+}
+
+/// Setup the global tracing subscriber, and capture `log` events.
+///
+/// # Panics
+///
+/// If the global subscriber has already been set, because then things are very wrong.
+///
+pub(crate) fn init(
+	identifier: &str,
+	env_prefix: &str,
+	settings: &Settings,
+	metrics: &mut MetricsRegistry,
+) {
+	// Problems we come across before the subscriber is running get logged once it is
+	let mut warnings: Vec<String> = Vec::new();
+
+	let sink_env_var = format!("{env_prefix}_LOG_SINK");
+	let sink = match env::var(&sink_env_var) {
+		Ok(val) => val.parse::<LogSink>().unwrap_or_else(|e| {
+			warnings.push(format!("Ignoring value of {sink_env_var}: {e}"));
+			settings.sink.clone()
+		}),
+		Err(VarError::NotUnicode(_)) => {
+			warnings.push(format!(
+				"Ignoring value of {sink_env_var}: not valid unicode"
+			));
+			settings.sink.clone()
+		}
+		Err(VarError::NotPresent) => settings.sink.clone(),
+	};
+
+	let sink_layer = sink.layer(identifier).unwrap_or_else(|e| {
+		warnings.push(format!(
+			"Could not send logs to {sink} ({e}); logging to stderr instead"
+		));
+		stderr_layer()
+	});
+
+	let filter = env_filter(env_prefix, settings.default_level, &mut warnings);
+
+	let sub = subscriber(sink_layer, filter, settings, metrics);
+	// Spans are exported regardless of what's being logged, so the exporter gets its own filter
+	let sub = sub.with(otel_layer(identifier, settings, &mut warnings));
+	#[allow(clippy::expect_used)] // If this fails to start, we're in big trouble
+	tracing::subscriber::set_global_default(sub).expect("tracing subscriber failed to start");
+	if let Err(e) = tracing_log::LogTracer::init() {
+		tracing::warn!("Failed to initialize LogTracer: {e}");
+	}
+
+	for w in warnings {
+		tracing::warn!("{w}");
+	}
+}
+
+/// Put together the whole subscriber, with the given layer taking care of actually writing out
+/// the events that make it through the filter and rate limiting.
+///
+/// The filter and rate limiting only apply to the sink (and the count of events logged), rather
+/// than the whole subscriber, so that any other layers added on top see everything.
+pub(crate) fn subscriber(
+	sink: impl Layer<Registry> + Send + Sync + 'static,
+	filter: EnvFilter,
+	settings: &Settings,
+	metrics: &mut MetricsRegistry,
+) -> impl tracing::Subscriber + for<'a> LookupSpan<'a> + Send + Sync + 'static {
+	let filter: Box<dyn Filter<Registry> + Send + Sync> = match settings.rate_limit {
+		Some((burst, per_second)) => {
+			let rate_limit = RateLimitLayer::new(burst, per_second);
+			rate_limit.register_metrics(metrics);
+			rate_limit.start_summaries();
+			FilterExt::boxed(filter.and(rate_limit))
+		}
+		None => FilterExt::boxed(filter),
+	};
+
+	let event_counter = EventCounterLayer::new().with_targets(settings.count_by_target);
+	event_counter.register_metrics(metrics);
+
+	Registry::default().with(sink.and_then(event_counter).with_filter(filter))
+}
+
+/// Build the layer that exports spans to an OpenTelemetry collector, unless that has been
+/// turned off with `OTEL_SDK_DISABLED=true`.
+///
+/// Spans at or above the default level are exported, whatever `<PREFIX>_LOG` says.
+#[cfg(feature = "opentelemetry")]
+fn otel_layer<S>(
+	identifier: &str,
+	settings: &Settings,
+	warnings: &mut Vec<String>,
+) -> Option<impl Layer<S>>
+where
+	S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+	if otel::disabled() {
+		return None;
+	}
+
+	otel::layer(identifier, settings.trace_sample_ratio)
+		.map(|layer| layer.with_filter(default_filter(settings.default_level)))
+		.map_err(|e| warnings.push(format!("Not exporting traces: {e}")))
+		.ok()
+}
+
+#[cfg(not(feature = "opentelemetry"))]
+fn otel_layer(
+	_identifier: &str,
+	_settings: &Settings,
+	_warnings: &mut Vec<String>,
+) -> Option<tracing_subscriber::layer::Identity> {
+	None
+}
+
+/// Send any buffered telemetry on its way.
+///
+/// Spans exported to an OpenTelemetry collector are sent in batches, so if the service is about
+/// to exit of its own accord, calling this first makes sure the last few spans aren't lost.  The
+/// skeleton does this itself before exiting.  Without the `opentelemetry` feature, there's
+/// nothing to flush, and this does nothing.
+///
+pub fn flush() {
+	#[cfg(feature = "opentelemetry")]
+	otel::flush();
+}
+
+/// A filter which lets through everything at or above the given level, and nothing else.
+pub(crate) fn default_filter(level: LevelFilter) -> EnvFilter {
+	EnvFilter::builder()
+		.with_default_directive(level.into())
+		.parse_lossy("")
+}
+
+/// Build the filter that decides which events get logged.
+///
+/// The directives are taken from `<PREFIX>_LOG` if it is set, otherwise `RUST_LOG`, and anything
+/// that isn't covered by the directives is logged if it is at or above the default level.
+/// Directives that can't be parsed are skipped, and a warning added to the list.
+///
+fn env_filter(
+	env_prefix: &str,
+	default_level: LevelFilter,
+	warnings: &mut Vec<String>,
+) -> EnvFilter {
+	let mut filter = default_filter(default_level);
+
+	for var in [
+		format!("{env_prefix}_LOG"),
+		EnvFilter::DEFAULT_ENV.to_string(),
+	] {
+		match env::var(&var) {
+			Ok(directives) => {
+				for directive in directives.split(',').filter(|d| !d.trim().is_empty()) {
+					match directive.trim().parse::<Directive>() {
+						Ok(d) => filter = filter.add_directive(d),
+						Err(e) => warnings.push(format!(
+							"Ignoring invalid log directive '{directive}' in {var}: {e}"
+						)),
+					}
+				}
+				break;
+			}
+			Err(VarError::NotUnicode(_)) => {
+				warnings.push(format!("Ignoring value of {var}: not valid unicode"));
+			}
+			Err(VarError::NotPresent) => (),
+		}
+	}
+
+	filter
+}
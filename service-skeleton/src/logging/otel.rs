@@ -0,0 +1,65 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter};
+use opentelemetry_sdk::{
+	trace::{Sampler, SdkTracer, SdkTracerProvider},
+	Resource,
+};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+use std::{env, sync::OnceLock};
+
+/// The provider behind the layer, kept around so that buffered spans can be flushed on the way
+/// out.
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Build a layer which batches up spans and sends them to an OTLP collector over HTTP.
+///
+/// Where the spans go (and how) is controlled by the standard `OTEL_EXPORTER_OTLP_*` environment
+/// variables.  The service name and sample ratio given are used unless `OTEL_SERVICE_NAME` or
+/// `OTEL_TRACES_SAMPLER` (respectively) say otherwise.
+///
+pub(crate) fn layer<S>(
+	service_name: &str,
+	sample_ratio: Option<f64>,
+) -> Result<OpenTelemetryLayer<S, SdkTracer>, ExporterBuildError>
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	let exporter = SpanExporter::builder().with_http().build()?;
+
+	let mut resource = Resource::builder();
+	if env::var_os("OTEL_SERVICE_NAME").is_none() {
+		resource = resource.with_service_name(service_name.to_string());
+	}
+
+	let mut builder = SdkTracerProvider::builder()
+		.with_batch_exporter(exporter)
+		.with_resource(resource.build());
+	if let Some(ratio) = sample_ratio {
+		if env::var_os("OTEL_TRACES_SAMPLER").is_none() {
+			builder = builder.with_sampler(Sampler::ParentBased(Box::new(
+				Sampler::TraceIdRatioBased(ratio),
+			)));
+		}
+	}
+
+	let provider = builder.build();
+	let tracer = provider.tracer(service_name.to_string());
+	PROVIDER.set(provider).ok();
+
+	Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Whether the standard `OTEL_SDK_DISABLED` variable says to leave well enough alone.
+pub(crate) fn disabled() -> bool {
+	env::var("OTEL_SDK_DISABLED").is_ok_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+pub(crate) fn flush() {
+	if let Some(provider) = PROVIDER.get() {
+		// There's nowhere sensible to report a failure to, when we're probably on our way out
+		provider.force_flush().ok();
+	}
+}
@@ -0,0 +1,223 @@
+use parking_lot::Mutex;
+use prometheus_client::{
+	encoding::EncodeLabelSet,
+	metrics::{counter::Counter, family::Family},
+	registry::Registry,
+};
+use tracing::{callsite::Identifier, subscriber::Interest, Event, Metadata, Subscriber};
+use tracing_subscriber::{
+	filter::LevelFilter,
+	layer::{Context, Filter},
+	Layer,
+};
+
+use std::{
+	collections::HashMap,
+	sync::{Arc, Weak},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// The target that suppression summaries are logged under; events with this target are never
+/// rate limited themselves.
+const SUMMARY_TARGET: &str = "service_skeleton::logging::rate_limit";
+
+/// A tracing layer which limits how often each individual log statement can emit events.
+///
+/// Every callsite (that is, each `tracing::warn!()` or similar in the code) gets its own allowance
+/// of events: up to `burst` events can be emitted in quick succession, after which events are
+/// allowed through at no more than `per_second` per second.  Any events in excess of that are
+/// dropped, and every so often (ten seconds, by default) a summary of how many events were
+/// suppressed from each callsite is logged.
+///
+/// The number of suppressed events is also available as a metric, once registered with
+/// [`register_metrics`](Self::register_metrics).
+///
+/// As a layer, it stops rate limited events from reaching any other layer.  To rate limit only
+/// some layers, use it as a per-layer [`Filter`] instead, in which case call
+/// [`start_summaries`](Self::start_summaries) yourself.
+///
+#[derive(Debug)]
+pub struct RateLimitLayer {
+	buckets: Arc<Mutex<HashMap<Identifier, Bucket>>>,
+	// Times are kept as offsets from this, so that they can saturate, rather than overflow
+	epoch: Instant,
+	interval: Duration,
+	tolerance: Duration,
+	summary_interval: Duration,
+	suppressed: Family<SuppressedLabels, Counter>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+	metadata: &'static Metadata<'static>,
+	// The "theoretical arrival time" of the next event, in GCRA parlance, since the epoch
+	tat: Duration,
+	// How many events have been suppressed since the last summary
+	suppressed: u64,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct SuppressedLabels {
+	target: String,
+	callsite: String,
+}
+
+impl RateLimitLayer {
+	/// Create a new rate limiting layer.
+	///
+	/// Each callsite can emit up to `burst` events at once, and is then limited to `per_second`
+	/// events per second.  A `per_second` of zero means that each callsite gets its burst, and
+	/// then nothing more, ever.
+	#[must_use]
+	pub fn new(burst: u32, per_second: u32) -> Self {
+		// For a rate of zero, the interval is as long as it can be while still leaving room for
+		// the burst, so that the burst gets through, but whatever comes after it never does
+		let interval = Duration::from_secs(1)
+			.checked_div(per_second)
+			.or_else(|| Duration::MAX.checked_div(burst.max(1)))
+			.unwrap_or(Duration::MAX);
+
+		Self {
+			buckets: Arc::default(),
+			epoch: Instant::now(),
+			interval,
+			tolerance: interval.saturating_mul(burst.saturating_sub(1)),
+			summary_interval: Duration::from_secs(10),
+			suppressed: Family::default(),
+		}
+	}
+
+	/// Change how often the summaries of suppressed events are logged.
+	#[must_use]
+	pub fn with_summary_interval(self, summary_interval: Duration) -> Self {
+		Self {
+			summary_interval,
+			..self
+		}
+	}
+
+	/// Add the metric that counts suppressed events to a registry.
+	pub fn register_metrics(&self, registry: &mut Registry) {
+		registry.register(
+			"log_events_suppressed",
+			"Number of log events dropped by rate limiting",
+			self.suppressed.clone(),
+		);
+	}
+
+	/// Start logging summaries of the suppressed events, which carries on until the limiter is
+	/// dropped.
+	///
+	/// This happens by itself when the limiter is added as a layer, but not when it's used as a
+	/// filter.
+	pub fn start_summaries(&self) {
+		let buckets = Arc::downgrade(&self.buckets);
+		let every = self.summary_interval;
+
+		// If the thread doesn't start, we just don't get summaries; not worth getting upset over
+		thread::Builder::new()
+			.name("LogRateLimitSummary".to_string())
+			.spawn(move || summarise(&buckets, every))
+			.ok();
+	}
+
+	/// Whether an event from the callsite described by `metadata` is allowed through right now.
+	fn allow(&self, metadata: &'static Metadata<'static>) -> bool {
+		if metadata.target() == SUMMARY_TARGET {
+			return true;
+		}
+
+		let now = self.epoch.elapsed();
+		let mut buckets = self.buckets.lock();
+		let bucket = buckets
+			.entry(metadata.callsite())
+			.or_insert_with(|| Bucket {
+				metadata,
+				tat: now,
+				suppressed: 0,
+			});
+
+		let tat = bucket.tat.max(now);
+		if tat.saturating_sub(now) > self.tolerance {
+			bucket.suppressed = bucket.suppressed.saturating_add(1);
+			self.suppressed
+				.get_or_create(&SuppressedLabels {
+					target: metadata.target().to_string(),
+					callsite: callsite_location(metadata),
+				})
+				.inc();
+			false
+		} else {
+			bucket.tat = tat.saturating_add(self.interval);
+			true
+		}
+	}
+}
+
+impl<S: Subscriber> Layer<S> for RateLimitLayer {
+	fn on_layer(&mut self, _subscriber: &mut S) {
+		self.start_summaries();
+	}
+
+	fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+		self.allow(event.metadata())
+	}
+}
+
+impl<S: Subscriber> Filter<S> for RateLimitLayer {
+	fn enabled(&self, _metadata: &Metadata<'_>, _ctx: &Context<'_, S>) -> bool {
+		true
+	}
+
+	fn callsite_enabled(&self, _metadata: &'static Metadata<'static>) -> Interest {
+		Interest::always()
+	}
+
+	fn event_enabled(&self, event: &Event<'_>, _ctx: &Context<'_, S>) -> bool {
+		self.allow(event.metadata())
+	}
+
+	// Nothing is ruled out by level, so this doesn't get in the way of other filters' hints
+	fn max_level_hint(&self) -> Option<LevelFilter> {
+		Some(LevelFilter::TRACE)
+	}
+}
+
+fn callsite_location(metadata: &Metadata<'_>) -> String {
+	format!(
+		"{}:{}",
+		metadata.file().unwrap_or("<unknown>"),
+		metadata.line().unwrap_or_default()
+	)
+}
+
+fn summarise(buckets: &Weak<Mutex<HashMap<Identifier, Bucket>>>, every: Duration) {
+	loop {
+		thread::sleep(every);
+
+		let Some(buckets) = buckets.upgrade() else {
+			return;
+		};
+
+		// Collect everything up first, so we're not holding the lock while logging
+		let suppressions: Vec<(&'static Metadata<'static>, u64)> = buckets
+			.lock()
+			.values_mut()
+			.filter(|b| b.suppressed > 0)
+			.map(|b| (b.metadata, std::mem::take(&mut b.suppressed)))
+			.collect();
+		drop(buckets);
+
+		for (metadata, count) in suppressions {
+			tracing::warn!(
+				target: SUMMARY_TARGET,
+				suppressed = count,
+				"Suppressed {count} similar {} messages from {} ({})",
+				metadata.level(),
+				metadata.target(),
+				callsite_location(metadata),
+			);
+		}
+	}
+}
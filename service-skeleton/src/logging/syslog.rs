@@ -0,0 +1,159 @@
+use tracing::{span, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use std::{
+	fmt::Write as _,
+	io,
+	os::unix::net::UnixDatagram,
+	path::Path,
+	process,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::fields::{event_spans, record_new_span, record_span_values, FieldValues};
+
+/// The syslog facility we log everything under (`daemon`, naturally).
+const FACILITY: u8 = 3;
+
+/// A tracing layer which sends events to a local syslog daemon, formatted as per [RFC
+/// 5424](https://www.rfc-editor.org/rfc/rfc5424).
+///
+/// Events are logged with the `daemon` facility.  The message part of each entry looks much like
+/// what you'd see on stderr: the spans the event occurred in (with their fields), then the message
+/// itself, then the event's fields as `name=value` pairs.
+///
+#[derive(Debug)]
+pub struct SyslogLayer {
+	app_name: String,
+	hostname: String,
+	socket: UnixDatagram,
+}
+
+impl SyslogLayer {
+	/// Create a new layer that sends events to the syslog daemon listening on `socket`,
+	/// identifying itself as `app_name`.
+	///
+	/// The usual location for the syslog socket is `/dev/log`.
+	pub fn new(app_name: impl AsRef<str>, socket: impl AsRef<Path>) -> io::Result<Self> {
+		let sock = UnixDatagram::unbound()?;
+		sock.connect(socket)?;
+
+		Ok(Self {
+			app_name: header_value(app_name.as_ref(), 48),
+			hostname: header_value(&hostname(), 255),
+			socket: sock,
+		})
+	}
+}
+
+impl<S> Layer<S> for SyslogLayer
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+		record_new_span(attrs, id, &ctx);
+	}
+
+	fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+		record_span_values(id, values, &ctx);
+	}
+
+	fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+		let meta = event.metadata();
+		let mut values = FieldValues::from_event(event);
+
+		// Writing to a String can't fail, so all the write!() results are safe to ignore
+		let mut msg = format!(
+			"<{}>1 {} {} {} {} - - ",
+			pri(*meta.level()),
+			timestamp(SystemTime::now()),
+			self.hostname,
+			self.app_name,
+			process::id(),
+		);
+
+		for (name, span_values) in event_spans(event, &ctx) {
+			msg.push_str(name);
+			if !span_values.0.is_empty() {
+				msg.push('{');
+				for (i, (field, value)) in span_values.0.iter().enumerate() {
+					if i > 0 {
+						msg.push(' ');
+					}
+					write!(msg, "{field}={value}").ok();
+				}
+				msg.push('}');
+			}
+			msg.push_str(": ");
+		}
+
+		write!(msg, "{}: ", meta.target()).ok();
+		msg.push_str(&values.take_message().unwrap_or_default());
+		for (field, value) in values.0 {
+			write!(msg, " {field}={value}").ok();
+		}
+
+		// There's nowhere sensible to report a failure to log, so we don't
+		self.socket.send(msg.as_bytes()).ok();
+	}
+}
+
+fn pri(level: Level) -> u8 {
+	let severity: u8 = match level {
+		Level::ERROR => 3,
+		Level::WARN => 4,
+		Level::INFO => 6,
+		Level::DEBUG | Level::TRACE => 7,
+	};
+
+	FACILITY.saturating_mul(8).saturating_add(severity)
+}
+
+/// Header fields in RFC 5424 have to be printable US-ASCII with no spaces, and are limited in
+/// length.  A header field with nothing in it is written as `-`.
+fn header_value(s: &str, max_len: usize) -> String {
+	let mut v: String = s.chars().filter(char::is_ascii_graphic).collect();
+	v.truncate(max_len);
+	if v.is_empty() {
+		"-".to_string()
+	} else {
+		v
+	}
+}
+
+fn hostname() -> String {
+	["/proc/sys/kernel/hostname", "/etc/hostname"]
+		.iter()
+		.find_map(|f| std::fs::read_to_string(f).ok())
+		.map(|h| h.trim().to_string())
+		.unwrap_or_default()
+}
+
+/// Render a time as an RFC 3339 timestamp in UTC, with microsecond precision.
+// All the arithmetic in here is on values that are well within range, and the divisions are
+// intentionally integer divisions
+#[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+fn timestamp(t: SystemTime) -> String {
+	let since_epoch = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+	let secs = since_epoch.as_secs();
+	let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+	// Howard Hinnant's civil_from_days algorithm, for dates after 1970
+	let z = days + 719_468;
+	let era = z / 146_097;
+	let doe = z - era * 146_097;
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + u64::from(month <= 2);
+
+	format!(
+		"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+		secs_of_day / 3600,
+		secs_of_day % 3600 / 60,
+		secs_of_day % 60,
+		since_epoch.subsec_micros(),
+	)
+}
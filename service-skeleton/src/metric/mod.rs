@@ -67,6 +67,34 @@ pub(crate) fn store_metric(name: impl AsRef<str>, metric: impl Any + Send + Sync
 	metrics.insert(name.as_ref().to_string(), Arc::new(metric));
 }
 
+/// Whether a metric with the given name has been registered.
+pub(crate) fn is_registered(name: impl AsRef<str>) -> bool {
+	#[allow(clippy::expect_used)] // If this explodes, we're all in a world of hurt
+	let metrics = metrics().lock().expect("METRICS mutex to not be poisoned");
+
+	metrics.contains_key(name.as_ref())
+}
+
+/// Set the gauge with the given labels to `1`, and get rid of any other labels it had; this is
+/// for "info" metrics, whose labels carry the information.
+pub(crate) fn set_info<L>(name: impl AsRef<str>, labels: &L)
+where
+	L: Clone + Eq + Send + Sync + Hash + 'static,
+{
+	#[allow(clippy::expect_used)] // If this explodes, we're all in a world of hurt
+	let m = metrics().lock().expect("METRICS mutex to not be poisoned");
+
+	if let Some(family) = m
+		.get(name.as_ref())
+		.and_then(|any_family| any_family.downcast_ref::<Family<L, Gauge>>())
+	{
+		family.clear();
+		family.get_or_create(labels).set(1);
+	} else {
+		tracing::warn!("No info metric named {}", name.as_ref());
+	}
+}
+
 pub fn counter<L>(name: impl AsRef<str>, labels: &L, f: impl Fn(MappedRwLockReadGuard<'_, Counter>))
 where
 	L: Clone + Eq + Send + Sync + Hash + 'static,
@@ -0,0 +1,204 @@
+//! The command-line options that every service gets for free.
+//!
+
+use std::{env, process::exit};
+
+use super::Service;
+use crate::{
+	config::{cli, render_table, EnvDocs, Sources},
+	logging, ServiceConfig,
+};
+
+/// Something to do instead of running the service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Command {
+	/// `--check-config`: read the config, report any problems, and exit
+	CheckConfig,
+	/// `--print-config`: show the config, and where each value came from
+	PrintConfig,
+	/// `--env-docs [table|markdown|dotenv]`: describe all the env vars the service takes
+	EnvDocs(DocsFormat),
+	/// `--version`: say what version of the service this is
+	Version,
+	/// `--help`: describe the command-line flags for the config's fields
+	Help,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum DocsFormat {
+	Table,
+	Markdown,
+	Dotenv,
+}
+
+impl Command {
+	/// Figure out the command from the program's arguments (less the program name), if the first
+	/// of them is one of ours.  Anything else is left for the service to deal with, including
+	/// `--help`, unless the config can be set with command-line flags (`cli_flags`).
+	#[allow(clippy::print_stderr)] // This is the user interface
+	pub(super) fn from_args(
+		mut args: impl Iterator<Item = String>,
+		cli_flags: bool,
+	) -> Option<Self> {
+		match args.next()?.as_str() {
+			"--check-config" => Some(Self::CheckConfig),
+			"--print-config" => Some(Self::PrintConfig),
+			"--version" => Some(Self::Version),
+			"--help" if cli_flags => Some(Self::Help),
+			"--env-docs" => match args.next().as_deref() {
+				None | Some("table") => Some(Self::EnvDocs(DocsFormat::Table)),
+				Some("markdown") => Some(Self::EnvDocs(DocsFormat::Markdown)),
+				Some("dotenv") => Some(Self::EnvDocs(DocsFormat::Dotenv)),
+				Some(other) => {
+					eprintln!(
+						"Unknown env docs format '{other}' (expected one of table, markdown, or dotenv)"
+					);
+					#[allow(clippy::exit)] // Can't do what was asked
+					exit(2);
+				}
+			},
+			_ => None,
+		}
+	}
+}
+
+/// Print the name and version of the service.
+#[allow(clippy::print_stdout)] // This is the user interface
+pub(super) fn version(svc: &Service) -> ! {
+	match &svc.version {
+		Some(version) => println!("{} {version}", svc.name),
+		None => println!("{}", svc.name),
+	}
+
+	#[allow(clippy::exit)] // All done
+	exit(0);
+}
+
+/// Print the documentation for every env var that the service takes.
+#[allow(clippy::print_stdout)] // This is the user interface
+pub(super) fn env_docs<CFG: ServiceConfig>(svc: &Service, format: DocsFormat) -> ! {
+	let docs = EnvDocs::for_service::<CFG>(&svc.name);
+
+	match format {
+		DocsFormat::Table => println!("{}", docs.table()),
+		DocsFormat::Markdown => println!("{}", docs.markdown()),
+		DocsFormat::Dotenv => println!("{}", docs.dotenv()),
+	}
+
+	#[allow(clippy::exit)] // All done
+	exit(0);
+}
+
+/// Describe the built-in options, and the flags for all the config's fields.
+#[allow(clippy::print_stdout)] // This is the user interface
+pub(super) fn help<CFG: ServiceConfig>(svc: &Service, env_prefix: &str) -> ! {
+	let mut rows = vec![
+		["--check-config", "Check the configuration, and exit"],
+		[
+			"--print-config",
+			"Print the configuration, and where it came from, and exit",
+		],
+		[
+			"--env-docs [table|markdown|dotenv]",
+			"Describe all the environment variables, and exit",
+		],
+		["--version", "Print the version, and exit"],
+		["--help", "Print this help, and exit"],
+	]
+	.map(|row| row.map(String::from))
+	.to_vec();
+	rows.extend(cli::help::<CFG>(env_prefix));
+
+	let options = render_table(&rows)
+		.lines()
+		.map(|line| format!("  {line}"))
+		.collect::<Vec<_>>()
+		.join("\n");
+	println!("Usage: {} [OPTIONS]\n\nOptions:\n{options}", svc.name);
+
+	#[allow(clippy::exit)] // All done
+	exit(0);
+}
+
+/// Read the config, and say whether it's any good, exiting with a status that says the same.
+///
+/// Every problem with the config is logged, just as it would be if the service were started.
+///
+#[allow(clippy::print_stdout)] // This is the user interface
+pub(super) fn check_config<CFG: ServiceConfig>(
+	svc: &Service,
+	env_prefix: &str,
+	args: &[String],
+) -> ! {
+	load_config::<CFG>(&svc.name, env_prefix, args);
+
+	println!("Configuration for {} is OK", svc.name);
+
+	#[allow(clippy::exit)] // All done
+	exit(0);
+}
+
+/// Print the config, with secrets redacted, along with where the value for each variable came
+/// from.
+#[allow(clippy::print_stdout)] // This is the user interface
+pub(super) fn print_config<CFG: ServiceConfig>(
+	svc: &Service,
+	env_prefix: &str,
+	args: &[String],
+) -> ! {
+	// This has to be done before the config is read, because sensitive vars get removed from
+	// the environment once they have been read
+	let origins = match cli::vars_with_args::<CFG>(env_prefix, env::vars(), args.iter().cloned())
+		.and_then(|(vars, flags)| {
+			Sources::new(env_prefix, vars.into_iter()).map(|sources| sources.with_flags(flags))
+		}) {
+		Ok(sources) => {
+			let mut rows = vec![["VARIABLE", "SOURCE"].map(String::from)];
+			rows.extend(
+				EnvDocs::for_service::<CFG>(&svc.name)
+					.vars()
+					.iter()
+					.map(|var| {
+						let origin = sources.origin(var).map_or_else(
+							|| {
+								if var.default_text().is_some() {
+									"default".to_string()
+								} else {
+									"not set".to_string()
+								}
+							},
+							|source| source.to_string(),
+						);
+						[var.display_name(), origin]
+					}),
+			);
+			render_table(&rows)
+		}
+		// load_config will report the problem
+		Err(_) => String::new(),
+	};
+
+	let cfg = load_config::<CFG>(&svc.name, env_prefix, args);
+
+	#[allow(clippy::use_debug)] // The redacted Debug output is what there is to show
+	println!("{:#?}\n\n{origins}", cfg.redacted());
+
+	#[allow(clippy::exit)] // All done
+	exit(0);
+}
+
+/// Read the config, or log all the problems with it and exit.
+pub(super) fn load_config<CFG: ServiceConfig>(
+	name: &str,
+	env_prefix: &str,
+	args: &[String],
+) -> CFG {
+	CFG::from_env_vars_and_args(env_prefix, env::vars(), args.iter().cloned()).unwrap_or_else(|e| {
+		for problem in e.problems() {
+			tracing::error!("Failed to configure {name}: {problem}");
+		}
+		logging::flush();
+		#[allow(clippy::exit)] // nothing else useful going to be going on after this
+		exit(1);
+	})
+}
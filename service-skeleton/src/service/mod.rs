@@ -7,19 +7,26 @@ use prometheus_client::{
 	metrics::{counter::Counter, family::Family, gauge::Gauge, histogram::Histogram},
 	registry::{Metric, Registry},
 };
-use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::filter::LevelFilter;
 
 use std::{
-	env::{self, vars as env_vars, VarError},
+	env::{self, VarError},
 	fmt::Debug,
 	hash::Hash,
+	io::ErrorKind,
 	panic::{catch_unwind, UnwindSafe},
+	path::{Path, PathBuf},
 	process::exit,
 };
 
+mod commands;
+use commands::Command;
+
 use crate::{
+	config::{dotenv, publish_info, INFO_METRIC, RELOADS_METRIC},
+	logging::{self, LogSink},
 	metric::{start_metrics_server, store_metric, Histogrammer},
-	ServiceConfig,
+	Error, ServiceConfig,
 };
 
 /// Create a new service skeleton.
@@ -31,6 +38,10 @@ pub fn service(name: impl AsRef<str>) -> Service {
 	Service {
 		name: name.as_ref().to_string(),
 		registry: Registry::default(),
+		logging: logging::Settings::default(),
+		dotenv: cfg!(debug_assertions).then(|| PathBuf::from(".env")),
+		version: None,
+		config_info: false,
 	}
 }
 
@@ -38,6 +49,10 @@ pub fn service(name: impl AsRef<str>) -> Service {
 pub struct Service {
 	name: String,
 	registry: Registry,
+	logging: logging::Settings,
+	dotenv: Option<PathBuf>,
+	version: Option<String>,
+	config_info: bool,
 }
 
 impl Service {
@@ -73,6 +88,136 @@ impl Service {
 		)
 	}
 
+	/// Choose where log messages should be sent.
+	///
+	/// By default, logs are written to stderr.  Whatever is chosen here can be overridden at
+	/// runtime with the `<PREFIX>_LOG_SINK` environment variable; see [`LogSink`] for details.
+	///
+	#[must_use]
+	pub fn log_sink(mut self, sink: LogSink) -> Self {
+		self.logging.sink = sink;
+		self
+	}
+
+	/// Set the level of log messages that are emitted by default.
+	///
+	/// Events at this level or more severe will be logged, unless the log filter directives in the
+	/// `<PREFIX>_LOG` environment variable (or, if that isn't set, `RUST_LOG`) say otherwise.
+	/// Those variables take the [same directive
+	/// syntax](https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html#directives)
+	/// as `RUST_LOG` always has.
+	///
+	/// If not set, the default level is `INFO`.
+	///
+	/// With the `opentelemetry` feature, this is also the level of the spans that get exported,
+	/// which `<PREFIX>_LOG` has no say over.
+	///
+	#[must_use]
+	pub fn default_log_level(mut self, level: impl Into<LevelFilter>) -> Self {
+		self.logging.default_level = level.into();
+		self
+	}
+
+	/// Limit how many log events each log statement in the service can emit.
+	///
+	/// Each statement can emit up to `burst` events in quick succession, after which further
+	/// events are dropped unless they come in at no more than `per_second` per second (so a
+	/// `per_second` of zero allows each statement its burst, and nothing more).  A summary
+	/// of how many events were dropped is logged every ten seconds, and the running total is
+	/// available as the `<service>_log_events_suppressed` metric.
+	///
+	/// By default, a burst of 100 events is allowed, followed by 10 per second.
+	///
+	#[must_use]
+	pub fn log_rate_limit(mut self, burst: u32, per_second: u32) -> Self {
+		self.logging.rate_limit = Some((burst, per_second));
+		self
+	}
+
+	/// Turn off log rate limiting entirely, letting every log event through.
+	#[must_use]
+	pub fn without_log_rate_limit(mut self) -> Self {
+		self.logging.rate_limit = None;
+		self
+	}
+
+	/// Include the target of each log event in the `<service>_log_events` metric.
+	///
+	/// Every log event is counted, by level, in the `<service>_log_events` metric.  Counting
+	/// them by target as well can help narrow down where problems are coming from, at the cost of
+	/// (potentially many) more time series.
+	///
+	#[must_use]
+	pub fn count_log_events_by_target(mut self) -> Self {
+		self.logging.count_by_target = true;
+		self
+	}
+
+	/// Load environment variables from the given `.env` file when the service starts.
+	///
+	/// Variables which are already set in the environment are left alone, so the file only
+	/// provides fallback values.  If the file doesn't exist, nothing happens, but if it can't be
+	/// parsed, the service won't start.
+	///
+	/// In debug builds, variables are loaded from `.env` in the current directory by default; in
+	/// release builds, nothing is loaded unless this is called.
+	///
+	#[must_use]
+	pub fn dotenv(mut self, path: impl Into<PathBuf>) -> Self {
+		self.dotenv = Some(path.into());
+		self
+	}
+
+	/// Don't load environment variables from a `.env` file, even in debug builds.
+	#[must_use]
+	pub fn without_dotenv(mut self) -> Self {
+		self.dotenv = None;
+		self
+	}
+
+	/// Set the version of the service, as reported by `--version`.
+	///
+	/// Usually, this will be `env!("CARGO_PKG_VERSION")`.
+	///
+	#[must_use]
+	pub fn version(mut self, version: impl Into<String>) -> Self {
+		self.version = Some(version.into());
+		self
+	}
+
+	/// Publish the service's configuration as the `<service>_config_info` metric.
+	///
+	/// The metric is always `1`, and has a label for each config field (named after the field,
+	/// with the names of any nested configs it's within joined on with double underscores, so
+	/// `db.host` is `db__host`), except for those that are `sensitive`, `encrypted`, or marked
+	/// `#[config(hidden)]`.  The
+	/// `config_hash` label is a hash of all the fields that aren't secret, `hidden` or not, so
+	/// replicas whose configs have drifted apart stand out on a dashboard.
+	///
+	#[must_use]
+	pub fn config_info_metric(mut self) -> Self {
+		self.config_info = true;
+		self
+	}
+
+	/// Set the proportion of traces that are exported to the OpenTelemetry collector.
+	///
+	/// The ratio should be between `0.0` (export nothing) and `1.0` (export everything, which is
+	/// the default).  Spans which are part of a trace that was sampled by an upstream service
+	/// are always exported.  The standard `OTEL_TRACES_SAMPLER` and `OTEL_TRACES_SAMPLER_ARG`
+	/// environment variables, if set, take precedence over whatever is set here.
+	///
+	#[cfg(feature = "opentelemetry")]
+	#[must_use]
+	pub fn trace_sample_ratio(mut self, ratio: f64) -> Self {
+		self.logging.trace_sample_ratio = Some(ratio);
+		self
+	}
+
+	pub(crate) fn logging_settings(&self) -> &logging::Settings {
+		&self.logging
+	}
+
 	fn add_metric(
 		mut self,
 		name: impl AsRef<str>,
@@ -95,6 +240,23 @@ impl Service {
 	/// as the entrypoint to the service to be run.  If that function exits (which it shouldn't), or
 	/// panics (which it definitely shouldn't, but might), it will be restarted.
 	///
+	/// If the first command-line argument is one of these, the service isn't run; instead:
+	///
+	/// * `--check-config` reads the config, logs any problems with it, and exits with a status of
+	///   `0` if there weren't any, and `1` otherwise;
+	/// * `--print-config` prints the config (with secrets redacted), along with where the value of
+	///   each environment variable came from (the environment, a command-line flag, or the
+	///   config file);
+	/// * `--env-docs [table|markdown|dotenv]` describes all the environment variables that the
+	///   service takes (see [`EnvDocs`](crate::EnvDocs));
+	/// * `--version` prints the name of the service, and its [`version`](Self::version); and
+	/// * `--help` describes all of these, along with the flags for setting config values, if the
+	///   config is derived with `#[config(cli)]`.
+	///
+	/// Any other arguments are left alone, for the service to make of them what it will, other
+	/// than flags for config fields (see
+	/// [`from_env_vars_and_args`](crate::ServiceConfig::from_env_vars_and_args)).
+	///
 	///
 	/// # Panics
 	///
@@ -102,26 +264,58 @@ impl Service {
 	/// logger cannot be started, or if the service configuration cannot be correctly extracted from
 	/// the environment.
 	///
-	pub fn run<CFG>(self, svc: impl FnOnce(CFG) + Clone + UnwindSafe) -> !
+	pub fn run<CFG>(mut self, svc: impl FnOnce(CFG) + Clone + UnwindSafe) -> !
 	where
-		CFG: ServiceConfig + Clone + Debug + Sync + Send + UnwindSafe,
+		CFG: ServiceConfig + Clone + Sync + Send + UnwindSafe,
 	{
-		let layer = tracing_tree::HierarchicalLayer::default()
-			.with_writer(tracing_subscriber::fmt::TestWriter::new())
-			.with_indent_lines(true)
-			.with_indent_amount(2)
-			.with_targets(true);
-
-		let sub = tracing_subscriber::registry::Registry::default()
-			.with(layer)
-			.with(tracing_subscriber::EnvFilter::from_default_env());
-		#[allow(clippy::expect_used)] // If this fails to start, we're in big trouble
-		tracing::subscriber::set_global_default(sub).expect("tracing subscriber failed to start");
-		if let Err(e) = tracing_log::LogTracer::init() {
-			tracing::warn!("Failed to initialize LogTracer: {e}");
+		let env_prefix = AsShoutySnekCase(&self.name).to_string();
+
+		let args: Vec<String> = env::args().skip(1).collect();
+		let command = Command::from_args(args.iter().cloned(), CFG::cli_flags());
+		match command {
+			Some(Command::Version) => commands::version(&self),
+			Some(Command::EnvDocs(format)) => commands::env_docs::<CFG>(&self, format),
+			Some(Command::Help) => commands::help::<CFG>(&self, &env_prefix),
+			Some(Command::CheckConfig | Command::PrintConfig) | None => (),
 		}
 
-		let env_prefix = AsShoutySnekCase(&self.name).to_string();
+		let snake_name = AsSnekCase(&self.name).to_string();
+
+		// This has to happen before logging starts, as the log settings can come from the file
+		let dotenv_result = self
+			.dotenv
+			.as_deref()
+			.map(|path| (path, dotenv::load(path)));
+
+		logging::init(
+			&snake_name,
+			&env_prefix,
+			&self.logging,
+			self.registry.sub_registry_with_prefix(&snake_name),
+		);
+
+		if let Some((path, result)) = dotenv_result {
+			report_dotenv(path, result);
+		}
+
+		match command {
+			Some(Command::CheckConfig) => commands::check_config::<CFG>(&self, &env_prefix, &args),
+			Some(Command::PrintConfig) => commands::print_config::<CFG>(&self, &env_prefix, &args),
+			Some(Command::Version | Command::EnvDocs(_) | Command::Help) | None => (),
+		}
+
+		if self.config_info {
+			self = self.gauge::<Vec<(String, String)>>(
+				INFO_METRIC,
+				"The service's configuration, in the labels",
+			);
+		}
+		if CFG::reloadable() {
+			self = self.counter::<Vec<(&'static str, &'static str)>>(
+				RELOADS_METRIC,
+				"Number of times the config has been reloaded, by outcome",
+			);
+		}
 
 		let metrics_port_env_var = format!("{env_prefix}_METRICS_SERVER_PORT");
 
@@ -136,13 +330,10 @@ impl Service {
 			Err(VarError::NotPresent) => tracing::info!("Not starting metrics server: {metrics_port_env_var} is not set"),
 		}
 
-		let cfg = CFG::from_env_vars(&env_prefix, env_vars()).unwrap_or_else(|e| {
-			tracing::error!("Failed to configure {}: {e}", self.name);
-			#[allow(clippy::exit)] // nothing else useful going to be going on after this
-			exit(1);
-		});
+		let cfg: CFG = commands::load_config(&self.name, &env_prefix, &args);
 
-		tracing::debug!("Using config: {cfg:?}");
+		tracing::debug!("Using config: {:?}", cfg.redacted());
+		publish_info(&cfg);
 
 		loop {
 			let svc_fn = svc.clone();
@@ -159,3 +350,26 @@ impl Service {
 		}
 	}
 }
+
+fn report_dotenv(path: &Path, result: Result<Vec<String>, Error>) {
+	match result {
+		Ok(vars) => tracing::info!(
+			"Loaded {} variable(s) from {}: {}",
+			vars.len(),
+			path.display(),
+			vars.join(", ")
+		),
+		Err(Error::ConfigFileRead { cause, .. }) if cause.kind() == ErrorKind::NotFound => {
+			tracing::debug!(
+				"Not loading variables from {}: file not found",
+				path.display()
+			);
+		}
+		Err(e) => {
+			tracing::error!("Failed to load variables from {}: {e}", path.display());
+			logging::flush();
+			#[allow(clippy::exit)] // nothing else useful going to be going on after this
+			exit(1);
+		}
+	}
+}
@@ -0,0 +1,193 @@
+//! Helpers for testing services built with the skeleton.
+//!
+//! [`Service::run`](crate::Service::run) takes over the whole process, logging included, which
+//! makes it hard to check that a service logs what it should.  A [`LogCapture`] runs a piece of
+//! code with a (thread-local) subscriber made from the same layers the skeleton uses, except that
+//! instead of being written out, each event is kept for later inspection.
+//!
+//! ```rust
+//! use service_skeleton::testing::LogCapture;
+//!
+//! let capture = LogCapture::new();
+//! capture.run(|| {
+//!     let _span = tracing::info_span!("frobnicate", id = 42).entered();
+//!     tracing::warn!(widget = "sprocket", "Frobnication failed");
+//! });
+//!
+//! let events = capture.events();
+//! assert_eq!(1, events.len());
+//! assert_eq!(tracing::Level::WARN, events[0].level);
+//! assert_eq!("Frobnication failed", events[0].message);
+//! assert_eq!(Some("sprocket"), events[0].field("widget"));
+//! assert_eq!(vec!["frobnicate"], events[0].span_names());
+//! assert_eq!(Some("42"), events[0].spans[0].field("id"));
+//! ```
+//!
+
+use parking_lot::Mutex;
+use prometheus_client::registry::Registry as MetricsRegistry;
+use tracing::{span, Event, Level, Subscriber};
+use tracing_subscriber::{filter::LevelFilter, layer::Context, registry::LookupSpan, Layer};
+
+use std::sync::Arc;
+
+use crate::{
+	logging::{
+		self,
+		fields::{event_spans, record_new_span, record_span_values, FieldValues},
+	},
+	Service,
+};
+
+/// Captures the log events emitted while running some code.
+///
+/// The events go through the same filtering and rate limiting as they would in a running
+/// service, with the default settings, or those of a particular service if the capture is
+/// created with [`for_service`](Self::for_service).  Log filter directives in the environment
+/// are *not* consulted, so tests get the same results wherever they're run.
+///
+#[derive(Debug, Default)]
+pub struct LogCapture {
+	settings: logging::Settings,
+	events: Arc<Mutex<Vec<CapturedEvent>>>,
+}
+
+impl LogCapture {
+	/// Create a new log capture, using the skeleton's default logging settings.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a new log capture, using the logging settings of the given service.
+	#[must_use]
+	pub fn for_service(svc: &Service) -> Self {
+		Self {
+			settings: svc.logging_settings().clone(),
+			..Self::default()
+		}
+	}
+
+	/// Change the level of events that are captured.
+	///
+	/// Works just like [`Service::default_log_level`](crate::Service::default_log_level), which
+	/// means that, by default, events at `INFO` or above are captured.
+	///
+	#[must_use]
+	pub fn default_log_level(mut self, level: impl Into<LevelFilter>) -> Self {
+		self.settings.default_level = level.into();
+		self
+	}
+
+	/// Run the given function, capturing all the log events it emits on the current thread.
+	///
+	/// This can be called as many times as you like; the captured events accumulate.
+	///
+	pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+		let sub = logging::subscriber(
+			CaptureLayer(Arc::clone(&self.events)),
+			logging::default_filter(self.settings.default_level),
+			&self.settings,
+			&mut MetricsRegistry::default(),
+		);
+
+		tracing::subscriber::with_default(sub, f)
+	}
+
+	/// All the events captured so far, in the order they were emitted.
+	#[must_use]
+	pub fn events(&self) -> Vec<CapturedEvent> {
+		self.events.lock().clone()
+	}
+
+	/// Forget about all the events captured so far.
+	pub fn clear(&self) {
+		self.events.lock().clear();
+	}
+}
+
+/// A log event which was emitted while a [`LogCapture`] was running.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CapturedEvent {
+	/// How bad things are.
+	pub level: Level,
+	/// The target of the event, which is usually the module path it came from.
+	pub target: String,
+	/// The formatted log message, or an empty string if there wasn't one.
+	pub message: String,
+	/// All the event's fields other than the message, in the order they were given.
+	pub fields: Vec<(&'static str, String)>,
+	/// The spans the event was emitted in, from the outermost inwards.
+	pub spans: Vec<CapturedSpan>,
+}
+
+impl CapturedEvent {
+	/// The value of the named field, if the event has it.
+	#[must_use]
+	pub fn field(&self, name: &str) -> Option<&str> {
+		find_field(&self.fields, name)
+	}
+
+	/// The names of the spans the event was emitted in, from the outermost inwards.
+	#[must_use]
+	pub fn span_names(&self) -> Vec<&'static str> {
+		self.spans.iter().map(|s| s.name).collect()
+	}
+}
+
+/// One of the spans that a [`CapturedEvent`] was emitted in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CapturedSpan {
+	/// The name of the span.
+	pub name: &'static str,
+	/// The fields recorded against the span, as of when the event was emitted.
+	pub fields: Vec<(&'static str, String)>,
+}
+
+impl CapturedSpan {
+	/// The value of the named field, if the span has it.
+	#[must_use]
+	pub fn field(&self, name: &str) -> Option<&str> {
+		find_field(&self.fields, name)
+	}
+}
+
+fn find_field<'a>(fields: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+	fields
+		.iter()
+		.find(|(n, _)| *n == name)
+		.map(|(_, v)| v.as_str())
+}
+
+struct CaptureLayer(Arc<Mutex<Vec<CapturedEvent>>>);
+
+impl<S> Layer<S> for CaptureLayer
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+{
+	fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+		record_new_span(attrs, id, &ctx);
+	}
+
+	fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+		record_span_values(id, values, &ctx);
+	}
+
+	fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+		let metadata = event.metadata();
+		let mut values = FieldValues::from_event(event);
+
+		self.0.lock().push(CapturedEvent {
+			level: *metadata.level(),
+			target: metadata.target().to_string(),
+			message: values.take_message().unwrap_or_default(),
+			fields: values.0,
+			spans: event_spans(event, &ctx)
+				.into_iter()
+				.map(|(name, FieldValues(fields))| CapturedSpan { name, fields })
+				.collect(),
+		});
+	}
+}
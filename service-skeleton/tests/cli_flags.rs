@@ -0,0 +1,155 @@
+mod common;
+use common::vars;
+
+use service_skeleton::{Error, ServiceConfig};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DbConfig {
+	host: String,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+#[config(cli)]
+struct FlagConfig {
+	#[config(default_value = "World")]
+	name: String,
+
+	#[config(default_value = "false")]
+	verbose: bool,
+
+	#[config(default_value = "")]
+	hosts: Vec<String>,
+
+	#[config(sensitive, from_file)]
+	password: Option<String>,
+
+	#[config(nested)]
+	db: DbConfig,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct NoFlagConfig {
+	#[config(default_value = "World")]
+	name: String,
+}
+
+fn args(args: &[&str]) -> impl Iterator<Item = String> {
+	args.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>()
+		.into_iter()
+}
+
+#[test]
+fn test_flags_override_env() {
+	let cfg = FlagConfig::from_env_vars_and_args(
+		"FLAGS",
+		vars(&[
+			("FLAGS_NAME", "Jaime"),
+			("FLAGS_DB_HOST", "env.example.com"),
+		]),
+		args(&[
+			"--name",
+			"Bobbie",
+			"--verbose",
+			"--db-host=flag.example.com",
+			"--something-for-the-service",
+			"--hosts",
+			"a",
+			"--hosts",
+			"b",
+		]),
+	)
+	.unwrap();
+
+	assert_eq!("Bobbie", cfg.name);
+	assert!(cfg.verbose);
+	assert_eq!("flag.example.com", cfg.db.host);
+	assert_eq!(vec!["a", "b"], cfg.hosts);
+
+	// Without any flags, it's all from the environment
+	let cfg = FlagConfig::from_env_vars_and_args(
+		"FLAGS",
+		vars(&[("FLAGS_DB_HOST", "env.example.com"), ("FLAGS_HOSTS", "x,y")]),
+		args(&[]),
+	)
+	.unwrap();
+	assert_eq!("World", cfg.name);
+	assert!(!cfg.verbose);
+	assert_eq!("env.example.com", cfg.db.host);
+	assert_eq!(vec!["x", "y"], cfg.hosts);
+}
+
+#[test]
+fn test_bool_flags_need_no_value() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	#[config(cli)]
+	struct BoolConfig {
+		dry_run: Option<bool>,
+
+		#[config(default_value = "false")]
+		quiet: std::primitive::bool,
+
+		#[config(default_value = "World")]
+		name: String,
+	}
+
+	let cfg = BoolConfig::from_env_vars_and_args(
+		"BOOLS",
+		vars(&[]),
+		args(&["--dry-run", "--quiet", "--name", "Bobbie"]),
+	)
+	.unwrap();
+
+	assert_eq!(Some(true), cfg.dry_run);
+	assert!(cfg.quiet);
+	assert_eq!("Bobbie", cfg.name);
+}
+
+#[test]
+fn test_sensitive_flags_refused() {
+	let err = FlagConfig::from_env_vars_and_args(
+		"FLAGS",
+		vars(&[("FLAGS_DB_HOST", "db")]),
+		args(&["--password", "hunter2"]),
+	)
+	.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigFlag { flag, .. } if flag == "--password"),
+		"{err:?}"
+	);
+
+	// Pointing at a file that has the secret in it is fine, though
+	let err = FlagConfig::from_env_vars_and_args(
+		"FLAGS",
+		vars(&[("FLAGS_DB_HOST", "db")]),
+		args(&["--password-file", "/this/does/not/exist"]),
+	)
+	.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigValueFileRead { var, .. } if var == "FLAGS_PASSWORD"),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn test_missing_flag_value() {
+	let err = FlagConfig::from_env_vars_and_args(
+		"FLAGS",
+		vars(&[("FLAGS_DB_HOST", "db")]),
+		args(&["--name"]),
+	)
+	.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigFlag { flag, .. } if flag == "--name"),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn test_flags_ignored_unless_enabled() {
+	let cfg =
+		NoFlagConfig::from_env_vars_and_args("NOFLAGS", vars(&[]), args(&["--name", "Bobbie"]))
+			.unwrap();
+	assert_eq!("World", cfg.name);
+}
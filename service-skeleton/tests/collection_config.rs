@@ -0,0 +1,285 @@
+mod common;
+
+use service_skeleton::{Error, ServiceConfig};
+
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	net::IpAddr,
+};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct ListConfig {
+	hosts: Vec<String>,
+
+	#[config(separator = ";", default_value = "a;b")]
+	features: HashSet<String>,
+
+	#[config(default_value = "")]
+	ports: Vec<u16>,
+
+	#[config(value_parser = parse_upper)]
+	shouty: Option<Vec<String>>,
+
+	labels: HashMap<String, u32>,
+
+	addresses: Option<BTreeMap<String, IpAddr>>,
+}
+
+fn parse_upper(s: &str) -> Result<String, String> {
+	Ok(s.to_uppercase())
+}
+
+fn load(vars: &[(&str, &str)]) -> Result<ListConfig, Error> {
+	ListConfig::from_env_vars("COLL", common::vars(vars))
+}
+
+#[test]
+fn test_separated_values() {
+	let cfg = load(&[
+		("COLL_HOSTS", "one.example.com, two.example.com,,"),
+		("COLL_FEATURES", "x;y;x"),
+		("COLL_PORTS", "80,443"),
+		("COLL_SHOUTY", "hello,world"),
+	])
+	.unwrap();
+
+	assert_eq!(vec!["one.example.com", "two.example.com"], cfg.hosts);
+	assert_eq!(
+		HashSet::from(["x".to_string(), "y".to_string()]),
+		cfg.features
+	);
+	assert_eq!(vec![80, 443], cfg.ports);
+	assert_eq!(
+		Some(vec!["HELLO".to_string(), "WORLD".to_string()]),
+		cfg.shouty
+	);
+	assert!(cfg.labels.is_empty());
+	assert_eq!(None, cfg.addresses);
+}
+
+#[test]
+fn test_numbered_values() {
+	let cfg = load(&[
+		("COLL_HOSTS_0", "one.example.com"),
+		("COLL_HOSTS_1", "two,with,commas.example.com"),
+		// Gaps end the list
+		("COLL_HOSTS_3", "never.example.com"),
+	])
+	.unwrap();
+
+	assert_eq!(
+		vec!["one.example.com", "two,with,commas.example.com"],
+		cfg.hosts
+	);
+}
+
+#[test]
+fn test_defaults_and_empty() {
+	let cfg = load(&[("COLL_HOSTS", "")]).unwrap();
+
+	assert!(cfg.hosts.is_empty());
+	assert_eq!(
+		HashSet::from(["a".to_string(), "b".to_string()]),
+		cfg.features
+	);
+	assert!(cfg.ports.is_empty());
+	assert_eq!(None, cfg.shouty);
+
+	assert!(matches!(
+		load(&[]),
+		Err(Error::ConfigValueRequired { var }) if var == "COLL_HOSTS"
+	));
+}
+
+#[test]
+fn test_maps() {
+	let cfg = load(&[
+		("COLL_HOSTS", "x"),
+		("COLL_LABELS_TEAM", "42"),
+		("COLL_LABELS_TIER", "3"),
+		("COLL_ADDRESSES_PRIMARY", "192.0.2.1"),
+	])
+	.unwrap();
+
+	assert_eq!(
+		HashMap::from([("TEAM".to_string(), 42), ("TIER".to_string(), 3)]),
+		cfg.labels
+	);
+	assert_eq!(
+		Some(BTreeMap::from([(
+			"PRIMARY".to_string(),
+			"192.0.2.1".parse().unwrap()
+		)])),
+		cfg.addresses
+	);
+}
+
+#[test]
+fn test_element_parse_errors() {
+	assert!(matches!(
+		load(&[("COLL_HOSTS", "x"), ("COLL_PORTS", "80,eighty")]),
+		Err(Error::ConfigValueParse { var, .. }) if var == "COLL_PORTS"
+	));
+	assert!(matches!(
+		load(&[("COLL_HOSTS", "x"), ("COLL_PORTS_0", "80"), ("COLL_PORTS_1", "eighty")]),
+		Err(Error::ConfigValueParse { var, .. }) if var == "COLL_PORTS_1"
+	));
+	assert!(matches!(
+		load(&[("COLL_HOSTS", "x"), ("COLL_LABELS_TEAM", "many")]),
+		Err(Error::ConfigValueParse { var, .. }) if var == "COLL_LABELS_TEAM"
+	));
+}
+
+#[test]
+fn test_from_config_file() {
+	let file = common::temp_file(
+		"collections.toml",
+		"hosts = [\"one\", \"two\"]\nports = [80, 443]\n\n[labels]\nteam = 42\nus-east = 7\n",
+	);
+
+	let cfg = load(&[("COLL_CONFIG_FILE", &file.to_string_lossy())]).unwrap();
+
+	assert_eq!(vec!["one", "two"], cfg.hosts);
+	assert_eq!(vec![80, 443], cfg.ports);
+	// Keys get turned into SHOUTY_SNAKE_CASE, the same as they'd have to be in the environment
+	assert_eq!(
+		HashMap::from([("TEAM".to_string(), 42), ("US_EAST".to_string(), 7)]),
+		cfg.labels
+	);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_sibling_fields_not_taken() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct SiblingConfig {
+		labels: HashMap<String, String>,
+
+		labels_extra: Option<String>,
+
+		#[config(default_value = "")]
+		labels_list: Vec<String>,
+	}
+
+	let cfg = SiblingConfig::from_env_vars(
+		"SIB",
+		common::vars(&[
+			("SIB_LABELS_TEAM", "core"),
+			("SIB_LABELS_EXTRA", "not a label"),
+			("SIB_LABELS_LIST_0", "nor this"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!(
+		HashMap::from([("TEAM".to_string(), "core".to_string())]),
+		cfg.labels
+	);
+	assert_eq!(Some("not a label"), cfg.labels_extra.as_deref());
+	assert_eq!(vec!["nor this"], cfg.labels_list);
+}
+
+#[test]
+fn test_skeleton_vars_not_taken() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct SkelConfig {
+		log: HashMap<String, String>,
+
+		metrics_server: HashMap<String, u16>,
+	}
+
+	let cfg = SkelConfig::from_env_vars(
+		"SKEL",
+		common::vars(&[
+			("SKEL_LOG_LEVEL", "loud"),
+			("SKEL_LOG_SINK", "stderr"),
+			("SKEL_METRICS_SERVER_BACKUP", "9000"),
+			("SKEL_METRICS_SERVER_PORT", "9543"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!(
+		HashMap::from([("LEVEL".to_string(), "loud".to_string())]),
+		cfg.log
+	);
+	assert_eq!(
+		HashMap::from([("BACKUP".to_string(), 9000)]),
+		cfg.metrics_server
+	);
+}
+
+#[test]
+fn test_outer_fields_not_taken() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct InnerConfig {
+		labels: HashMap<String, String>,
+	}
+
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct OuterConfig {
+		#[config(flatten)]
+		inner: InnerConfig,
+
+		labels_extra: Option<String>,
+	}
+
+	let cfg = OuterConfig::from_env_vars(
+		"OUTER",
+		common::vars(&[
+			("OUTER_LABELS_TEAM", "core"),
+			("OUTER_LABELS_EXTRA", "not a label"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!(
+		HashMap::from([("TEAM".to_string(), "core".to_string())]),
+		cfg.inner.labels
+	);
+	assert_eq!(Some("not a label"), cfg.labels_extra.as_deref());
+}
+
+#[test]
+fn test_sensitive_purge_spares_siblings() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct PurgeConfig {
+		#[config(sensitive)]
+		tokens: Vec<String>,
+
+		tokens_backup: String,
+
+		#[config(sensitive)]
+		secrets: HashMap<String, String>,
+
+		secrets_path: String,
+	}
+
+	let vars = [
+		("PURGE_TOKENS_0", "t0"),
+		("PURGE_TOKENS_1", "t1"),
+		("PURGE_TOKENS_BACKUP", "/backup"),
+		("PURGE_SECRETS_API", "s3kr1t"),
+		("PURGE_SECRETS_PATH", "/secrets"),
+	];
+	for (k, v) in vars {
+		std::env::set_var(k, v);
+	}
+
+	let cfg = PurgeConfig::from_env_vars("PURGE", common::vars(&vars)).unwrap();
+	assert_eq!(vec!["t0", "t1"], cfg.tokens);
+	assert_eq!(1, cfg.secrets.len());
+
+	for gone in ["PURGE_TOKENS_0", "PURGE_TOKENS_1", "PURGE_SECRETS_API"] {
+		assert!(std::env::var(gone).is_err(), "{gone} wasn't purged");
+	}
+	assert_eq!(
+		Ok("/backup".to_string()),
+		std::env::var("PURGE_TOKENS_BACKUP")
+	);
+	assert_eq!(
+		Ok("/secrets".to_string()),
+		std::env::var("PURGE_SECRETS_PATH")
+	);
+}
@@ -0,0 +1,33 @@
+//! Helpers shared by the integration tests.  Not every test uses all of them.
+#![allow(dead_code)]
+
+use std::{path::PathBuf, process::Command};
+
+/// Some env vars, in the form that `from_env_vars` wants them.
+pub fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+	vars.iter()
+		.map(|(k, v)| (k.to_string(), v.to_string()))
+		.collect::<Vec<_>>()
+		.into_iter()
+}
+
+/// Write `contents` to a file called `name` (more or less) in the temp dir, which won't clash with
+/// the files of any other test run.
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("service-skeleton-{}-{name}", std::process::id()));
+	std::fs::write(&path, contents).unwrap();
+	path
+}
+
+/// A command to re-run the test binary, running only `test_name`, with `child_var` set so that
+/// the test knows to play the child's part.
+///
+/// Tests that need to run a whole service, which takes over the process, use this to run the
+/// service in a child process.
+pub fn child_command(test_name: &str, child_var: &str) -> Command {
+	let mut cmd = Command::new(std::env::current_exe().unwrap());
+	cmd.args(["--exact", test_name, "--nocapture", "--test-threads=1"])
+		.env(child_var, "1")
+		.env_remove("RUST_LOG");
+	cmd
+}
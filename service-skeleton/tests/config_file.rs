@@ -0,0 +1,146 @@
+mod common;
+use common::temp_file;
+
+use service_skeleton::{Error, ServiceConfig};
+
+use std::path::PathBuf;
+
+#[derive(Debug, ServiceConfig)]
+struct FileConfig {
+	name: String,
+
+	#[config(default_value = "8080")]
+	port: u16,
+
+	#[config(default_value = "info")]
+	level: String,
+
+	db_host: String,
+
+	timeout: Option<u32>,
+}
+
+fn load(file: &PathBuf, env: &[(&str, &str)]) -> Result<FileConfig, Error> {
+	FileConfig::from_env_vars(
+		"FOO",
+		env.iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.chain([(
+				"FOO_CONFIG_FILE".to_string(),
+				file.to_string_lossy().to_string(),
+			)]),
+	)
+}
+
+#[test]
+fn test_toml_file() {
+	let file = temp_file(
+		"config.toml",
+		"name = \"Jaime\"\nport = 1234\n\n[db]\nhost = \"db.example.com\"\n",
+	);
+
+	let cfg = load(&file, &[]).unwrap();
+	assert_eq!("Jaime", cfg.name);
+	assert_eq!(1234, cfg.port);
+	assert_eq!("info", cfg.level);
+	assert_eq!("db.example.com", cfg.db_host);
+	assert_eq!(None, cfg.timeout);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_yaml_file() {
+	let file = temp_file(
+		"config.yaml",
+		"name: Jaime\ntimeout: 30\ndb:\n  host: db.example.com\n",
+	);
+
+	let cfg = load(&file, &[]).unwrap();
+	assert_eq!("Jaime", cfg.name);
+	assert_eq!(8080, cfg.port);
+	assert_eq!(Some(30), cfg.timeout);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_json_file() {
+	let file = temp_file(
+		"config.json",
+		r#"{"name": "Jaime", "level": "debug", "db": {"host": "db.example.com"}}"#,
+	);
+
+	let cfg = load(&file, &[]).unwrap();
+	assert_eq!("debug", cfg.level);
+	assert_eq!("db.example.com", cfg.db_host);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_env_overrides_file() {
+	let file = temp_file(
+		"override.toml",
+		"name = \"Jaime\"\nport = 1234\ndb_host = \"db.example.com\"\n",
+	);
+
+	let cfg = load(&file, &[("FOO_NAME", "Bobbie"), ("FOO_LEVEL", "warn")]).unwrap();
+	assert_eq!("Bobbie", cfg.name);
+	assert_eq!(1234, cfg.port);
+	assert_eq!("warn", cfg.level);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_parse_error_names_file_and_key() {
+	let file = temp_file(
+		"bad_value.toml",
+		"name = \"Jaime\"\ndb_host = \"x\"\nport = \"eleventy\"\n",
+	);
+
+	let err = load(&file, &[]).unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigFileValueParse { file: f, key, .. } if *f == file.display().to_string() && key == "port"),
+		"{err:?}"
+	);
+
+	// ... but if the bad value is coming from the environment, that's where we point
+	let err = load(&file, &[("FOO_PORT", "twelvty")]).unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigValueParse { var, .. } if var == "FOO_PORT"),
+		"{err:?}"
+	);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_bad_files() {
+	let file = temp_file("broken.toml", "name = ");
+	assert!(matches!(
+		load(&file, &[]),
+		Err(Error::ConfigFileParse { .. })
+	));
+	std::fs::remove_file(&file).unwrap();
+
+	let file = temp_file("config.ini", "name = Jaime");
+	assert!(matches!(
+		load(&file, &[]),
+		Err(Error::ConfigFileParse { .. })
+	));
+	std::fs::remove_file(&file).unwrap();
+
+	let file = temp_file("list.json", "[1, 2, 3]");
+	assert!(matches!(
+		load(&file, &[]),
+		Err(Error::ConfigFileParse { .. })
+	));
+	std::fs::remove_file(&file).unwrap();
+
+	assert!(matches!(
+		load(&PathBuf::from("/this/does/not/exist.toml"), &[]),
+		Err(Error::ConfigFileRead { .. })
+	));
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use service_skeleton::{config::info_labels, ServiceConfig};
+
+use std::{
+	collections::{HashMap, HashSet},
+	path::PathBuf,
+	time::Duration,
+};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DbConfig {
+	host: String,
+
+	#[config(default_value = "5432")]
+	port: u16,
+
+	#[config(sensitive)]
+	password: String,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct InfoConfig {
+	#[config(default_value = "World")]
+	name: String,
+
+	#[config(default = Duration::from_secs(30))]
+	timeout: Duration,
+
+	retries: Option<u32>,
+
+	#[config(hidden)]
+	instance_id: String,
+
+	#[config(sensitive)]
+	api_key: String,
+
+	#[config(nested)]
+	db: DbConfig,
+
+	#[config(nested, sensitive)]
+	secret_db: DbConfig,
+}
+
+fn load(vars: &[(&str, &str)]) -> InfoConfig {
+	let base = [
+		("INFO_INSTANCE_ID", "i-1234"),
+		("INFO_API_KEY", "hunter2"),
+		("INFO_DB_HOST", "db.example.com"),
+		("INFO_DB_PASSWORD", "swordfish"),
+		("INFO_SECRET_DB_HOST", "secret.example.com"),
+		("INFO_SECRET_DB_PASSWORD", "correct horse"),
+	];
+
+	InfoConfig::from_env_vars("INFO", common::vars(&[&base[..], vars].concat())).unwrap()
+}
+
+#[test]
+fn test_info_labels() {
+	let labels = info_labels(&load(&[]));
+
+	let names: Vec<_> = labels.iter().map(|(name, _)| name.as_str()).collect();
+	assert_eq!(
+		vec![
+			"name",
+			"timeout",
+			"retries",
+			"db__host",
+			"db__port",
+			"config_hash"
+		],
+		names
+	);
+
+	let value = |name: &str| {
+		labels
+			.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, v)| v.as_str())
+	};
+	assert_eq!(Some("World"), value("name"));
+	assert_eq!(Some("30s"), value("timeout"));
+	assert_eq!(Some(""), value("retries"));
+	assert_eq!(Some("db.example.com"), value("db__host"));
+	assert_eq!(Some("5432"), value("db__port"));
+
+	for (_, value) in &labels {
+		for secret in [
+			"hunter2",
+			"swordfish",
+			"correct horse",
+			"secret.example.com",
+		] {
+			assert!(!value.contains(secret), "{labels:?}");
+		}
+	}
+}
+
+#[test]
+fn test_config_hash() {
+	let hash = |cfg: &InfoConfig| {
+		info_labels(cfg)
+			.into_iter()
+			.find(|(name, _)| name == "config_hash")
+			.map(|(_, value)| value)
+			.unwrap()
+	};
+
+	let cfg = load(&[]);
+	assert_eq!(hash(&cfg), hash(&load(&[])));
+	assert_eq!(16, hash(&cfg).len());
+
+	assert_ne!(hash(&cfg), hash(&load(&[("INFO_NAME", "Bobbie")])));
+	// Hidden fields are still part of the config
+	assert_ne!(hash(&cfg), hash(&load(&[("INFO_INSTANCE_ID", "i-5678")])));
+	// ... but secrets aren't
+	assert_eq!(hash(&cfg), hash(&load(&[("INFO_API_KEY", "letmein")])));
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct RenderConfig {
+	motto: String,
+
+	#[config(env = "RENDER_KIND")]
+	r#type: String,
+
+	retries: Option<u32>,
+
+	data_dir: PathBuf,
+
+	servers: Vec<String>,
+
+	tags: HashSet<String>,
+
+	limits: HashMap<String, u32>,
+}
+
+fn load_render() -> RenderConfig {
+	RenderConfig::from_env_vars(
+		"RENDER",
+		common::vars(&[
+			("RENDER_MOTTO", r#"say "hi"\n"#),
+			("RENDER_KIND", "fancy"),
+			("RENDER_RETRIES", "3"),
+			("RENDER_DATA_DIR", "/var/lib/render"),
+			("RENDER_SERVERS", "zulu,alpha"),
+			("RENDER_TAGS", "c,a,e,b,d"),
+			("RENDER_LIMITS_ZED", "1"),
+			("RENDER_LIMITS_ALPHA", "2"),
+			("RENDER_LIMITS_MID", "3"),
+		]),
+	)
+	.unwrap()
+}
+
+#[test]
+fn test_label_values() {
+	let labels = info_labels(&load_render());
+
+	let value = |name: &str| {
+		labels
+			.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, v)| v.as_str())
+	};
+	assert_eq!(Some(r#"say "hi"\n"#), value("motto"));
+	assert_eq!(Some("fancy"), value("type"));
+	assert_eq!(Some("3"), value("retries"));
+	assert_eq!(Some("/var/lib/render"), value("data_dir"));
+	// Lists keep their order, but sets and maps get sorted
+	assert_eq!(Some("zulu,alpha"), value("servers"));
+	assert_eq!(Some("a,b,c,d,e"), value("tags"));
+	assert_eq!(Some("ALPHA=2,MID=3,ZED=1"), value("limits"));
+}
+
+#[test]
+fn test_config_hash_ignores_iteration_order() {
+	// Every HashSet and HashMap gets its own random iteration order, so loading the same config
+	// a few times is all it takes to catch any dependence on it
+	let labels = info_labels(&load_render());
+	for _ in 0..20 {
+		assert_eq!(labels, info_labels(&load_render()));
+	}
+}
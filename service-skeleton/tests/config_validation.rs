@@ -0,0 +1,127 @@
+use service_skeleton::{Error, ServiceConfig};
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, ServiceConfig)]
+#[config(validate = check_timeouts)]
+struct CheckedConfig {
+	#[config(range(min = 1, max = 65535), default_value = "8080")]
+	port: u32,
+
+	#[config(range(min = 0.0), default_value = "1.5")]
+	ratio: f64,
+
+	#[config(non_empty, regex = "^https?://")]
+	url: String,
+
+	#[config(one_of("debug", "info", "warn"), default_value = "info")]
+	level: String,
+
+	#[config(validate = check_even)]
+	even: Option<u32>,
+
+	#[config(non_empty, range(max = 10), default_value = "1,2")]
+	small_numbers: Vec<u8>,
+
+	#[config(non_empty)]
+	labels: Option<HashMap<String, String>>,
+
+	#[config(default_value = "5")]
+	connect_timeout: u32,
+
+	#[config(default_value = "30")]
+	request_timeout: u32,
+}
+
+fn check_even(n: &u32) -> Result<(), String> {
+	if n % 2 == 0 {
+		Ok(())
+	} else {
+		Err(format!("{n} is odd"))
+	}
+}
+
+fn check_timeouts(cfg: &CheckedConfig) -> Result<(), &'static str> {
+	if cfg.connect_timeout <= cfg.request_timeout {
+		Ok(())
+	} else {
+		Err("connect timeout can't be longer than the request timeout")
+	}
+}
+
+fn load(vars: &[(&str, &str)]) -> Result<CheckedConfig, Error> {
+	CheckedConfig::from_env_vars(
+		"CHK",
+		[("CHK_URL", "https://example.com")]
+			.iter()
+			.chain(vars.iter())
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect::<HashMap<_, _>>()
+			.into_iter(),
+	)
+}
+
+fn assert_invalid(vars: &[(&str, &str)], expected_var: &str, expected_rule: &str) {
+	let err = load(vars).unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigValueInvalid { var, rule } if var == expected_var && rule == expected_rule),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn test_valid_config() {
+	let cfg = load(&[("CHK_EVEN", "42"), ("CHK_LABELS_TEAM", "core")]).unwrap();
+
+	assert_eq!(8080, cfg.port);
+	assert_eq!(Some(42), cfg.even);
+	assert_eq!(vec![1, 2], cfg.small_numbers);
+}
+
+#[test]
+fn test_field_rules() {
+	assert_invalid(
+		&[("CHK_PORT", "0")],
+		"CHK_PORT",
+		"must be between 1 and 65535",
+	);
+	assert_invalid(&[("CHK_RATIO", "-1")], "CHK_RATIO", "must be at least 0");
+	assert_invalid(&[("CHK_URL", "")], "CHK_URL", "must not be empty");
+	assert_invalid(
+		&[("CHK_URL", "ftp://example.com")],
+		"CHK_URL",
+		"must match /^https?:///",
+	);
+	assert_invalid(
+		&[("CHK_LEVEL", "trace")],
+		"CHK_LEVEL",
+		"must be one of debug, info, warn",
+	);
+	assert_invalid(&[("CHK_EVEN", "3")], "CHK_EVEN", "3 is odd");
+}
+
+#[test]
+fn test_collection_rules() {
+	assert_invalid(
+		&[("CHK_SMALL_NUMBERS", "1,20")],
+		"CHK_SMALL_NUMBERS",
+		"must be at most 10",
+	);
+	assert_invalid(
+		&[("CHK_SMALL_NUMBERS", "")],
+		"CHK_SMALL_NUMBERS",
+		"must not be empty",
+	);
+
+	// An absent optional map isn't an empty one
+	assert!(load(&[]).unwrap().labels.is_none());
+}
+
+#[test]
+fn test_struct_rule() {
+	assert_invalid(
+		&[("CHK_CONNECT_TIMEOUT", "60")],
+		"CHK",
+		"connect timeout can't be longer than the request timeout",
+	);
+}
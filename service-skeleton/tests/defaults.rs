@@ -0,0 +1,99 @@
+mod common;
+use common::vars;
+
+use service_skeleton::{config::default_value_problems, EnvDocs, ServiceConfig};
+
+use std::{collections::HashMap, time::Duration};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DefaultConfig {
+	#[config(default = "World".to_string())]
+	name: String,
+
+	#[config(default = Duration::from_secs(30))]
+	timeout: Duration,
+
+	#[config(default)]
+	verbose: bool,
+
+	#[config(default = Some(3))]
+	retries: Option<u32>,
+
+	#[config(default = vec!["localhost".to_string()])]
+	hosts: Vec<String>,
+
+	#[config(default)]
+	labels: HashMap<String, String>,
+
+	#[config(default_value = "8080")]
+	port: u16,
+
+	#[config(default_value = "1m")]
+	interval: Duration,
+}
+
+#[test]
+fn test_defaults_used() {
+	let cfg = DefaultConfig::from_env_vars("DEFAULTS", vars(&[])).unwrap();
+
+	assert_eq!("World", cfg.name);
+	assert_eq!(Duration::from_secs(30), cfg.timeout);
+	assert!(!cfg.verbose);
+	assert_eq!(Some(3), cfg.retries);
+	assert_eq!(vec!["localhost"], cfg.hosts);
+	assert!(cfg.labels.is_empty());
+	assert_eq!(8080, cfg.port);
+	assert_eq!(Duration::from_secs(60), cfg.interval);
+}
+
+#[test]
+fn test_defaults_overridden() {
+	let cfg = DefaultConfig::from_env_vars(
+		"DEFAULTS",
+		vars(&[
+			("DEFAULTS_NAME", "Bobbie"),
+			("DEFAULTS_TIMEOUT", "5s"),
+			("DEFAULTS_VERBOSE", "true"),
+			("DEFAULTS_RETRIES", "7"),
+			("DEFAULTS_HOSTS", "a,b"),
+			("DEFAULTS_LABELS_TEAM", "core"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!("Bobbie", cfg.name);
+	assert_eq!(Duration::from_secs(5), cfg.timeout);
+	assert!(cfg.verbose);
+	assert_eq!(Some(7), cfg.retries);
+	assert_eq!(vec!["a", "b"], cfg.hosts);
+	assert_eq!(Some("core"), cfg.labels.get("TEAM").map(String::as_str));
+}
+
+#[test]
+fn test_default_docs() {
+	let docs = EnvDocs::for_service::<DefaultConfig>("defaults");
+	let vars = docs.vars();
+
+	assert_eq!(None, vars[0].default);
+	assert_eq!(Some("\"World\".to_string()"), vars[0].default_expr);
+	assert_eq!(Some("Duration::from_secs(30)"), vars[1].default_expr);
+	assert_eq!(Some("Default::default()"), vars[2].default_expr);
+	assert!(vars.iter().all(|var| !var.is_required()));
+
+	let markdown = docs.markdown();
+	assert!(
+		markdown.contains("* Default: `Duration::from_secs(30)`"),
+		"{markdown}"
+	);
+
+	let dotenv = docs.dotenv();
+	assert!(
+		dotenv.contains("# (Duration; default: Duration::from_secs(30))\n#DEFAULTS_TIMEOUT=\n"),
+		"{dotenv}"
+	);
+}
+
+#[test]
+fn test_default_values_checked() {
+	assert!(default_value_problems::<DefaultConfig>("DefaultConfig").is_empty());
+}
@@ -1,3 +1,6 @@
+mod common;
+use common::{temp_file, vars};
+
 use service_skeleton::ServiceConfig;
 
 #[test]
@@ -9,9 +12,7 @@ fn test_default_parse() {
 
 	assert_eq!(
 		None,
-		OptionConfig::from_env_vars("FOO", vec![].into_iter())
-			.unwrap()
-			.value
+		OptionConfig::from_env_vars("FOO", vars(&[])).unwrap().value
 	);
 }
 
@@ -30,13 +31,10 @@ fn test_parse_secrets() {
 
 	assert_eq!(
 		"s3kr1t",
-		SecretConfig::from_env_vars(
-			"FOO",
-			vec![("FOO_VALUE".to_string(), "s3kr1t".to_string())].into_iter()
-		)
-		.unwrap()
-		.value
-		.expose_secret()
+		SecretConfig::from_env_vars("FOO", vars(&[("FOO_VALUE", "s3kr1t")]))
+			.unwrap()
+			.value
+			.expose_secret()
 	);
 
 	assert_eq!(
@@ -47,6 +45,7 @@ fn test_parse_secrets() {
 
 #[test]
 fn test_encrypted_config() {
+	use secrecy::{ExposeSecret, SecretString};
 	use std::net::IpAddr;
 
 	#[derive(Debug, ServiceConfig)]
@@ -54,28 +53,178 @@ fn test_encrypted_config() {
 		name: String,
 
 		#[config(encrypted, key_file_field = "da_key")]
-		secret_string: String,
+		secret_string: SecretString,
 
 		#[config(encrypted, key_file_field = "da_key")]
-		maybe_secret: Option<String>,
+		maybe_secret: Option<SecretString>,
 
-		#[config(encrypted, key_file_field = "da_key")]
+		#[config(encrypted, allow_plaintext, key_file_field = "da_key")]
 		secret_address: IpAddr, // It was the first thing I could think of that impl'd FromStr
 	}
 
 	let cfg = SecretConfig::from_env_vars(
 		"FOO",
-		vec![
+		vars(&[
 			("FOO_NAME", "Jaime"),
 			("FOO_SECRET_STRING", "ssb1glggNkkrqpr3IZF-5bpSkhD0TvhEKmuHS0R2a-COwlRF8zxYObG49YNQecjHPEHbwxHPhzkiuZ0-KEzH8yqr-tFEmHCuouxW7x0INpNCeI91FE6AeNUyoPIuRpk8Iw"),
 			("FOO_SECRET_ADDRESS", "ssb1glggLFZmujR858TBxh3y_3o_uOo4v4q3nEdKzJ4h0Kgma1RYPrG49YNQ5Qd1hgtrbVLeUUFfZ4B9IkzXEnXMbeMFZTIwmVRYGgVF81Ur1rYMcBBx58DH6snP-Cpk25EGsLHT"),
 			// Tests run in the crate's root, not the workspace root
 			("FOO_DA_KEY", "./tests/test_encrypted_config.key"),
-		].into_iter().map(|(k, v)| (k.to_string(), v.to_string()))
+		]),
 	).unwrap();
 
 	assert_eq!("Jaime", cfg.name);
-	assert_eq!("s3kr1t", cfg.secret_string);
-	assert_eq!(None, cfg.maybe_secret);
+	assert_eq!("s3kr1t", cfg.secret_string.expose_secret());
+	assert!(cfg.maybe_secret.is_none());
 	assert_eq!("192.0.2.42".parse::<IpAddr>().unwrap(), cfg.secret_address);
 }
+
+#[test]
+fn test_redacted_debug() {
+	#[derive(Debug, ServiceConfig)]
+	struct LeakyConfig {
+		name: String,
+
+		#[config(sensitive)]
+		password: String,
+
+		#[config(encrypted, allow_plaintext, key_file_field = "da_key")]
+		secret_string: String,
+	}
+
+	let cfg = LeakyConfig {
+		name: "Jaime".to_string(),
+		password: "hunter2".to_string(),
+		secret_string: "s3kr1t".to_string(),
+	};
+
+	assert_eq!(
+		r#"LeakyConfig { name: "Jaime", password: [REDACTED], secret_string: [REDACTED] }"#,
+		format!("{:?}", cfg.redacted())
+	);
+	// The derived Debug is still as leaky as ever
+	assert!(format!("{cfg:?}").contains("hunter2"));
+	assert_eq!("hunter2", cfg.password);
+	assert_eq!("s3kr1t", cfg.secret_string);
+}
+
+#[test]
+fn test_derived_debug() {
+	#[derive(ServiceConfig)]
+	#[config(debug)]
+	struct SafeConfig {
+		name: String,
+
+		#[config(sensitive)]
+		password: String,
+	}
+
+	let cfg = SafeConfig {
+		name: "Jaime".to_string(),
+		password: "hunter2".to_string(),
+	};
+
+	assert_eq!(
+		r#"SafeConfig { name: "Jaime", password: [REDACTED] }"#,
+		format!("{cfg:?}")
+	);
+	assert_eq!(format!("{cfg:?}"), format!("{:?}", cfg.redacted()));
+	assert_eq!("hunter2", cfg.password);
+}
+
+#[test]
+fn test_from_file() {
+	use secrecy::{ExposeSecret, SecretString};
+
+	#[derive(Debug, ServiceConfig)]
+	struct FileConfig {
+		#[config(from_file)]
+		name: String,
+
+		#[config(from_file, sensitive)]
+		password: SecretString,
+
+		#[config(from_file)]
+		port: Option<u16>,
+	}
+
+	let secret_file = temp_file("password", "hunter2\n");
+
+	std::env::set_var("FF_PASSWORD_FILE", &secret_file);
+
+	let cfg = FileConfig::from_env_vars(
+		"FF",
+		vars(&[
+			("FF_NAME", "Jaime"),
+			("FF_PASSWORD_FILE", &secret_file.to_string_lossy()),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!("Jaime", cfg.name);
+	assert_eq!("hunter2", cfg.password.expose_secret());
+	assert_eq!(None, cfg.port);
+	assert_eq!(
+		Err(std::env::VarError::NotPresent),
+		std::env::var("FF_PASSWORD_FILE")
+	);
+
+	let err = FileConfig::from_env_vars(
+		"FF",
+		vars(&[
+			("FF_NAME", "Jaime"),
+			("FF_PASSWORD", "hunter2"),
+			("FF_PASSWORD_FILE", "/some/file"),
+		]),
+	)
+	.unwrap_err();
+	assert!(matches!(
+		err,
+		service_skeleton::Error::ConfigValueConflict { .. }
+	));
+
+	let err = FileConfig::from_env_vars(
+		"FF",
+		vars(&[
+			("FF_NAME", "Jaime"),
+			("FF_PASSWORD_FILE", "/this/does/not/exist"),
+		]),
+	)
+	.unwrap_err();
+	assert!(matches!(
+		err,
+		service_skeleton::Error::ConfigValueFileRead { .. }
+	));
+
+	std::fs::remove_file(&secret_file).unwrap();
+}
+
+#[test]
+fn test_all_errors_reported() {
+	#[derive(Debug, ServiceConfig)]
+	struct ManyConfig {
+		name: String,
+		port: u16,
+		#[config(default_value = "30")]
+		timeout: u32,
+		hosts: Vec<String>,
+	}
+
+	let err = ManyConfig::from_env_vars("MANY", vars(&[("MANY_PORT", "eleventy")])).unwrap_err();
+
+	let problems = err.problems();
+	assert_eq!(3, problems.len(), "{err:?}");
+	assert!(matches!(
+		problems[0],
+		service_skeleton::Error::ConfigValueRequired { var } if var == "MANY_NAME"
+	));
+	assert!(matches!(
+		problems[1],
+		service_skeleton::Error::ConfigValueParse { var, .. } if var == "MANY_PORT"
+	));
+	assert!(matches!(
+		problems[2],
+		service_skeleton::Error::ConfigValueRequired { var } if var == "MANY_HOSTS"
+	));
+	assert!(err.to_string().starts_with("3 problems with config: "));
+}
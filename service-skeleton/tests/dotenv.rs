@@ -0,0 +1,90 @@
+// These tests need to run a whole service, which takes over the process, so each test re-runs
+// the test binary with the service-running half of the test selected via an env var.
+
+mod common;
+use common::temp_file;
+
+use service_skeleton::{service, ServiceConfig};
+
+use std::{path::PathBuf, process::Output};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DottyConfig {
+	plain: String,
+	commented: String,
+	exported: String,
+	single: String,
+	double: String,
+	multiline: String,
+	real: String,
+}
+
+fn run_child(test_name: &str, dotenv: &PathBuf) -> Output {
+	common::child_command(test_name, "DOTENV_TEST_CHILD")
+		.env("DOTENV_TEST_FILE", dotenv)
+		.env("DOTTY_REAL", "from the environment")
+		.output()
+		.unwrap()
+}
+
+fn dotty_service() {
+	service("Dotty")
+		.dotenv(std::env::var("DOTENV_TEST_FILE").unwrap())
+		.run(|cfg: DottyConfig| {
+			println!("{cfg:?}");
+			std::process::exit(0);
+		});
+}
+
+#[test]
+fn test_dotenv_loading() {
+	if std::env::var("DOTENV_TEST_CHILD").is_ok() {
+		dotty_service();
+	}
+
+	let file = temp_file(
+		"good.env",
+		r#"
+# A comment
+DOTTY_PLAIN=overridden by the next one
+DOTTY_PLAIN=just a value
+DOTTY_COMMENTED = value  # with a comment
+export DOTTY_EXPORTED=exported
+DOTTY_SINGLE='no \n escapes # here'
+DOTTY_DOUBLE="some \"escapes\"\there" # and a comment
+DOTTY_MULTILINE="line one
+line two"
+DOTTY_REAL=from the file
+"#,
+	);
+
+	let output = run_child("test_dotenv_loading", &file);
+	let out = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{out}");
+
+	assert!(out.contains(r#"plain: "just a value""#), "{out}");
+	assert!(out.contains(r#"commented: "value""#), "{out}");
+	assert!(out.contains(r#"exported: "exported""#), "{out}");
+	assert!(out.contains(r#"single: "no \\n escapes # here""#), "{out}");
+	assert!(out.contains(r#"double: "some \"escapes\"\there""#), "{out}");
+	assert!(out.contains(r#"multiline: "line one\nline two""#), "{out}");
+	assert!(out.contains(r#"real: "from the environment""#), "{out}");
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_bad_dotenv() {
+	if std::env::var("DOTENV_TEST_CHILD").is_ok() {
+		dotty_service();
+	}
+
+	let file = temp_file("bad.env", "DOTTY_PLAIN=fine\nDOTTY_DOUBLE=\"never ends\n");
+
+	let output = run_child("test_bad_dotenv", &file);
+	let out = String::from_utf8_lossy(&output.stdout);
+	assert_eq!(Some(1), output.status.code(), "{out}");
+	assert!(out.contains("line 2: unterminated quoted value"), "{out}");
+
+	std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,193 @@
+use service_skeleton::{EnvDocs, EnvVarKind, ServiceConfig};
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DbConfig {
+	/// Where the database lives.
+	host: String,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DocConfig {
+	/// Who to say hello to.
+	///
+	/// Can be anyone at all.
+	#[config(default_value = "World")]
+	name: String,
+
+	/// The database password.
+	#[config(sensitive, from_file)]
+	password: String,
+
+	#[config(encrypted, allow_plaintext, key_file_field = "da_key")]
+	token: Option<String>,
+
+	/// Hosts to connect to.
+	hosts: Vec<String>,
+
+	labels: HashMap<String, u32>,
+
+	#[config(nested, sensitive)]
+	db: DbConfig,
+}
+
+#[test]
+fn test_env_var_metadata() {
+	let docs = EnvDocs::for_service::<DocConfig>("docs");
+
+	let names: Vec<_> = docs.vars().iter().map(|v| v.display_name()).collect();
+	assert_eq!(
+		vec![
+			"DOCS_NAME",
+			"DOCS_PASSWORD",
+			"DOCS_TOKEN",
+			"DOCS_HOSTS",
+			"DOCS_LABELS_<KEY>",
+			"DOCS_DB_HOST",
+			"DOCS_DA_KEY",
+			"DOCS_CONFIG_FILE",
+			"DOCS_LOG",
+			"DOCS_LOG_SINK",
+			"DOCS_METRICS_SERVER_PORT",
+		],
+		names
+	);
+
+	let vars = docs.vars();
+	assert_eq!(
+		"Who to say hello to.\n\nCan be anyone at all.",
+		vars[0].description
+	);
+	assert_eq!(Some("World"), vars[0].default);
+	assert!(!vars[0].is_required());
+	assert!(vars[1].sensitive && vars[1].from_file && vars[1].is_required());
+	assert!(vars[2].encrypted && vars[2].optional);
+	assert_eq!(Some("DOCS_DA_KEY"), vars[2].key_file.as_deref());
+	assert_eq!(EnvVarKind::List, vars[3].kind);
+	assert_eq!("Vec<String>", vars[3].type_name);
+	assert_eq!(EnvVarKind::Map, vars[4].kind);
+	assert_eq!("HashMap<String, u32>", vars[4].type_name);
+	assert!(!vars[4].is_required());
+	assert!(vars[5].sensitive);
+	assert!(!vars[6].is_required());
+}
+
+#[test]
+fn test_fields() {
+	let fields = DocConfig::fields("docs");
+
+	let names: Vec<_> = fields
+		.iter()
+		.map(|f| (f.field.as_deref().unwrap(), f.name.as_str()))
+		.collect();
+	assert_eq!(
+		vec![
+			("name", "DOCS_NAME"),
+			("password", "DOCS_PASSWORD"),
+			("token", "DOCS_TOKEN"),
+			("hosts", "DOCS_HOSTS"),
+			("labels", "DOCS_LABELS"),
+			("db.host", "DOCS_DB_HOST"),
+		],
+		names
+	);
+
+	assert_eq!(
+		"Who to say hello to.\n\nCan be anyone at all.",
+		fields[0].description
+	);
+	assert_eq!(Some("World"), fields[0].default);
+	assert!(fields[2].encrypted && fields[2].optional);
+	assert_eq!(Some("DOCS_DA_KEY"), fields[2].key_file.as_deref());
+	assert_eq!("Where the database lives.", fields[5].description);
+	assert!(fields[5].sensitive);
+
+	let docs = EnvDocs::for_service::<DocConfig>("docs");
+	let key_file = docs
+		.vars()
+		.iter()
+		.find(|v| v.name == "DOCS_DA_KEY")
+		.unwrap();
+	assert_eq!(None, key_file.field);
+}
+
+#[test]
+fn test_dotenv() {
+	let dotenv = EnvDocs::for_service::<DocConfig>("docs").dotenv();
+
+	assert!(
+		dotenv.starts_with(
+			"# Who to say hello to.
+#
+# Can be anyone at all.
+# (String)
+#DOCS_NAME=World
+
+# The database password.
+# (String; required; sensitive; can be read from the file named in DOCS_PASSWORD_FILE instead)
+DOCS_PASSWORD=
+
+# (String; encrypted with the key in DOCS_DA_KEY)
+#DOCS_TOKEN=
+
+# Hosts to connect to.
+# (Vec<String>; required; list; can be given as DOCS_HOSTS_0, DOCS_HOSTS_1, and so on instead)
+DOCS_HOSTS=
+
+# (HashMap<String, u32>)
+#DOCS_LABELS_<KEY>=
+"
+		),
+		"{dotenv}"
+	);
+}
+
+#[test]
+fn test_markdown() {
+	let markdown = EnvDocs::for_service::<DocConfig>("docs").markdown();
+
+	assert!(
+		markdown.starts_with(
+			"## `DOCS_NAME`
+
+Who to say hello to.
+
+Can be anyone at all.
+
+* Type: `String`
+* Default: `World`
+
+## `DOCS_PASSWORD`
+
+The database password.
+
+* Type: `String`
+* Required
+* Sensitive
+* Can be read from the file named in DOCS_PASSWORD_FILE instead
+"
+		),
+		"{markdown}"
+	);
+	assert!(
+		markdown.contains("## `DOCS_METRICS_SERVER_PORT`"),
+		"{markdown}"
+	);
+}
+
+#[test]
+fn test_table() {
+	let table = EnvDocs::for_service::<DocConfig>("docs").table();
+	let lines: Vec<_> = table.lines().collect();
+
+	assert!(lines[0].starts_with("VARIABLE "), "{table}");
+	assert!(lines[0].ends_with("  DESCRIPTION"), "{table}");
+	assert!(lines[1].starts_with("DOCS_NAME "), "{table}");
+	assert!(lines[1].ends_with("  Who to say hello to."), "{table}");
+	assert_eq!(
+		lines[0].find("TYPE"),
+		lines[4].find("Vec<String>"),
+		"{table}"
+	);
+}
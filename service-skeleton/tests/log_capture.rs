@@ -0,0 +1,66 @@
+use service_skeleton::{service, testing::LogCapture};
+use tracing::Level;
+
+#[test]
+fn test_captures_events() {
+	let capture = LogCapture::new();
+
+	let answer = capture.run(|| {
+		let span = tracing::info_span!("outer", request_id = 42u32);
+		let _outer = span.enter();
+		tracing::info_span!("inner").in_scope(|| {
+			tracing::warn!(user = "bob", attempts = 3, "Login failed");
+		});
+		tracing::debug!("too quiet to be captured");
+		42
+	});
+
+	assert_eq!(42, answer);
+
+	let events = capture.events();
+	assert_eq!(1, events.len(), "{events:?}");
+
+	let event = &events[0];
+	assert_eq!(Level::WARN, event.level);
+	assert_eq!("log_capture", event.target);
+	assert_eq!("Login failed", event.message);
+	assert_eq!(Some("bob"), event.field("user"));
+	assert_eq!(Some("3"), event.field("attempts"));
+	assert_eq!(None, event.field("message"));
+	assert_eq!(vec!["outer", "inner"], event.span_names());
+	assert_eq!(Some("42"), event.spans[0].field("request_id"));
+
+	capture.clear();
+	assert!(capture.events().is_empty());
+}
+
+#[test]
+fn test_uses_service_settings() {
+	let svc = service("Captured")
+		.default_log_level(Level::DEBUG)
+		.log_rate_limit(2, 1);
+	let capture = LogCapture::for_service(&svc);
+
+	capture.run(|| {
+		for i in 0..5 {
+			tracing::debug!("debug event {i}");
+		}
+	});
+
+	let messages: Vec<String> = capture.events().into_iter().map(|e| e.message).collect();
+	assert_eq!(vec!["debug event 0", "debug event 1"], messages);
+}
+
+#[test]
+fn test_default_log_level() {
+	let capture = LogCapture::new().default_log_level(Level::ERROR);
+
+	capture.run(|| {
+		tracing::warn!("not bad enough");
+		tracing::error!("bad enough");
+	});
+
+	let events = capture.events();
+	assert_eq!(1, events.len());
+	assert_eq!("bad enough", events[0].message);
+}
@@ -0,0 +1,50 @@
+use prometheus_client::{encoding::text, registry::Registry};
+use service_skeleton::logging::EventCounterLayer;
+use tracing_subscriber::layer::SubscriberExt as _;
+
+fn count_events(layer: EventCounterLayer) -> String {
+	let mut registry = Registry::default();
+	layer.register_metrics(registry.sub_registry_with_prefix("svc"));
+
+	tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+		tracing::error!("one");
+		tracing::error!(target: "elsewhere", "two");
+		tracing::warn!("three");
+	});
+
+	let mut metrics = String::new();
+	text::encode(&mut metrics, &registry).unwrap();
+	metrics
+}
+
+#[test]
+fn test_count_by_level() {
+	let metrics = count_events(EventCounterLayer::new());
+
+	assert!(
+		metrics.contains("svc_log_events_total{level=\"error\"} 2\n"),
+		"{metrics}"
+	);
+	assert!(
+		metrics.contains("svc_log_events_total{level=\"warn\"} 1\n"),
+		"{metrics}"
+	);
+}
+
+#[test]
+fn test_count_by_level_and_target() {
+	let metrics = count_events(EventCounterLayer::new().with_targets(true));
+
+	assert!(
+		metrics.contains("svc_log_events_total{level=\"error\",target=\"log_event_counter\"} 1\n"),
+		"{metrics}"
+	);
+	assert!(
+		metrics.contains("svc_log_events_total{level=\"error\",target=\"elsewhere\"} 1\n"),
+		"{metrics}"
+	);
+	assert!(
+		metrics.contains("svc_log_events_total{level=\"warn\",target=\"log_event_counter\"} 1\n"),
+		"{metrics}"
+	);
+}
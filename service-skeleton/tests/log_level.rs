@@ -0,0 +1,83 @@
+// These tests need to run a whole service, which takes over the process, so each test re-runs
+// the test binary with the service-running half of the test selected via an env var.
+
+mod common;
+
+use service_skeleton::service;
+
+fn run_child(test_name: &str, env: &[(&str, &str)]) -> String {
+	let mut cmd = common::child_command(test_name, "LOG_LEVEL_TEST_CHILD");
+	cmd.env_remove("LEVELLED_LOG");
+	for (k, v) in env {
+		cmd.env(k, v);
+	}
+	let output = cmd.output().unwrap();
+
+	String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn levelled_service() {
+	service("Levelled")
+		.default_log_level(tracing::Level::WARN)
+		.run(|_cfg: ()| {
+			tracing::info!("info event");
+			tracing::warn!("warn event");
+			tracing::debug!(target: "chatty", "chatty debug event");
+			std::process::exit(0);
+		});
+}
+
+#[test]
+fn test_default_log_level() {
+	if std::env::var("LOG_LEVEL_TEST_CHILD").is_ok() {
+		levelled_service();
+	}
+
+	let out = run_child("test_default_log_level", &[]);
+	assert!(out.contains("warn event"), "{out}");
+	assert!(!out.contains("info event"), "{out}");
+	assert!(!out.contains("chatty debug event"), "{out}");
+}
+
+#[test]
+fn test_service_log_var_overrides_rust_log() {
+	if std::env::var("LOG_LEVEL_TEST_CHILD").is_ok() {
+		levelled_service();
+	}
+
+	let out = run_child(
+		"test_service_log_var_overrides_rust_log",
+		&[("RUST_LOG", "error"), ("LEVELLED_LOG", "info,chatty=debug")],
+	);
+	assert!(out.contains("info event"), "{out}");
+	assert!(out.contains("warn event"), "{out}");
+	assert!(out.contains("chatty debug event"), "{out}");
+}
+
+#[test]
+fn test_rust_log_still_works() {
+	if std::env::var("LOG_LEVEL_TEST_CHILD").is_ok() {
+		levelled_service();
+	}
+
+	let out = run_child("test_rust_log_still_works", &[("RUST_LOG", "info")]);
+	assert!(out.contains("info event"), "{out}");
+	assert!(!out.contains("chatty debug event"), "{out}");
+}
+
+#[test]
+fn test_invalid_directive_warning() {
+	if std::env::var("LOG_LEVEL_TEST_CHILD").is_ok() {
+		levelled_service();
+	}
+
+	let out = run_child(
+		"test_invalid_directive_warning",
+		&[("LEVELLED_LOG", "chatty=debug,=lolwut=")],
+	);
+	assert!(
+		out.contains("Ignoring invalid log directive '=lolwut=' in LEVELLED_LOG"),
+		"{out}"
+	);
+	assert!(out.contains("chatty debug event"), "{out}");
+}
@@ -0,0 +1,98 @@
+use prometheus_client::{encoding::text, registry::Registry};
+use service_skeleton::logging::RateLimitLayer;
+use tracing::{field::Field, Event, Subscriber};
+use tracing_subscriber::{layer::Context, layer::SubscriberExt as _, Layer};
+
+use std::{
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<String>>>);
+
+impl<S: Subscriber> Layer<S> for Capture {
+	fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+		let mut message = String::new();
+		event.record(&mut |field: &Field, value: &dyn std::fmt::Debug| {
+			if field.name() == "message" {
+				message = format!("{value:?}");
+			}
+		});
+		self.0.lock().unwrap().push(message);
+	}
+}
+
+#[test]
+fn test_rate_limiting() {
+	let capture = Capture::default();
+	let layer = RateLimitLayer::new(3, 1).with_summary_interval(Duration::from_millis(200));
+	let mut registry = Registry::default();
+	layer.register_metrics(&mut registry);
+
+	// The summaries get logged from another thread, so this has to be the global subscriber
+	tracing::subscriber::set_global_default(
+		tracing_subscriber::registry()
+			.with(layer)
+			.with(capture.clone()),
+	)
+	.unwrap();
+
+	for i in 0..10 {
+		tracing::warn!("noisy warning {i}");
+	}
+	tracing::warn!("quiet warning");
+
+	assert_eq!(
+		vec![
+			"noisy warning 0",
+			"noisy warning 1",
+			"noisy warning 2",
+			"quiet warning"
+		],
+		*capture.0.lock().unwrap()
+	);
+
+	std::thread::sleep(Duration::from_millis(500));
+
+	let events = capture.0.lock().unwrap().clone();
+	assert_eq!(5, events.len(), "{events:?}");
+	assert!(
+		events[4].starts_with("Suppressed 7 similar WARN messages from log_rate_limit"),
+		"{events:?}"
+	);
+
+	let mut metrics = String::new();
+	text::encode(&mut metrics, &registry).unwrap();
+	assert!(
+		metrics.lines().any(|l| l
+			.starts_with("log_events_suppressed_total{target=\"log_rate_limit\"")
+			&& l.ends_with(" 7")),
+		"{metrics}"
+	);
+}
+
+#[test]
+fn test_zero_rate() {
+	let capture = Capture::default();
+	let subscriber = tracing_subscriber::registry()
+		.with(RateLimitLayer::new(2, 0))
+		.with(capture.clone());
+
+	tracing::subscriber::with_default(subscriber, || {
+		for i in 0..5 {
+			tracing::warn!("stifled warning {i}");
+		}
+		std::thread::sleep(Duration::from_millis(100));
+		tracing::warn!("too late");
+		for i in 5..10 {
+			tracing::warn!("stifled warning {i}");
+		}
+	});
+
+	// The burst gets through, and then nothing else from the same callsite ever does
+	assert_eq!(
+		vec!["stifled warning 0", "stifled warning 1", "too late"],
+		*capture.0.lock().unwrap()
+	);
+}
@@ -0,0 +1,137 @@
+#![cfg(unix)]
+
+use service_skeleton::logging::{JournaldLayer, LogSink, SyslogLayer};
+use tracing_subscriber::layer::SubscriberExt as _;
+
+use std::{os::unix::net::UnixDatagram, path::PathBuf, time::Duration};
+
+fn listener(name: &str) -> (UnixDatagram, PathBuf) {
+	let path = std::env::temp_dir().join(format!(
+		"service-skeleton-{name}-{}.sock",
+		std::process::id()
+	));
+	std::fs::remove_file(&path).ok();
+	let sock = UnixDatagram::bind(&path).unwrap();
+	sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+	(sock, path)
+}
+
+fn receive(sock: &UnixDatagram) -> Vec<u8> {
+	let mut buf = vec![0u8; 8192];
+	let len = sock.recv(&mut buf).unwrap();
+	buf.truncate(len);
+	buf
+}
+
+#[test]
+fn test_journald_fields() {
+	let (sock, path) = listener("journald");
+	let layer = JournaldLayer::new("test_svc", &path).unwrap();
+
+	tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+		let span = tracing::info_span!("handler", request_id = 42u32);
+		let _guard = span.enter();
+		tracing::warn!(user = "bob", "Something\nmultiline");
+	});
+
+	let msg = receive(&sock);
+	let text = String::from_utf8_lossy(&msg);
+
+	assert!(text.contains("PRIORITY=4\n"));
+	assert!(text.contains("SYSLOG_IDENTIFIER=test_svc\n"));
+	assert!(
+		text.contains("CODE_FILE=service-skeleton/tests/log_sinks.rs\n")
+			|| text.contains("CODE_FILE=tests/log_sinks.rs\n")
+	);
+	assert!(text.contains("CODE_LINE="));
+	assert!(text.contains("S0_NAME=handler\n"));
+	assert!(text.contains("S0_REQUEST_ID=42\n"));
+	assert!(text.contains("USER=bob\n"));
+
+	// Multi-line values get sent as a length-prefixed blob
+	let mut expected = b"MESSAGE\n".to_vec();
+	expected.extend_from_slice(&19u64.to_le_bytes());
+	expected.extend_from_slice(b"Something\nmultiline\n");
+	assert!(msg.windows(expected.len()).any(|w| w == expected));
+
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_journald_reserved_fields() {
+	let (sock, path) = listener("journald-reserved");
+	let layer = JournaldLayer::new("test_svc", &path).unwrap();
+
+	tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+		tracing::error!(
+			priority = 7,
+			syslog_identifier = "imposter",
+			s0_name = "fake",
+			f_priority = 1,
+			"Something broke"
+		);
+	});
+
+	let msg = receive(&sock);
+	let text = String::from_utf8_lossy(&msg);
+	let lines: Vec<&str> = text.lines().collect();
+
+	let priorities: Vec<_> = lines
+		.iter()
+		.filter(|line| line.starts_with("PRIORITY="))
+		.collect();
+	assert_eq!(vec![&"PRIORITY=3"], priorities, "{text}");
+	assert!(lines.contains(&"F_PRIORITY=7"), "{text}");
+	assert!(lines.contains(&"SYSLOG_IDENTIFIER=test_svc"), "{text}");
+	assert!(!lines.contains(&"SYSLOG_IDENTIFIER=imposter"), "{text}");
+	assert!(lines.contains(&"F_SYSLOG_IDENTIFIER=imposter"), "{text}");
+	assert!(lines.contains(&"F_S0_NAME=fake"), "{text}");
+	assert!(lines.contains(&"F_F_PRIORITY=1"), "{text}");
+
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_syslog_format() {
+	let (sock, path) = listener("syslog");
+	let layer = SyslogLayer::new("test_svc", &path).unwrap();
+
+	tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+		let span = tracing::info_span!("handler", request_id = 42u32);
+		let _guard = span.enter();
+		tracing::error!(user = "bob", "Something broke");
+	});
+
+	let msg = String::from_utf8(receive(&sock)).unwrap();
+
+	// daemon.err
+	assert!(msg.starts_with("<27>1 "), "unexpected header in {msg}");
+	let parts: Vec<&str> = msg.splitn(8, ' ').collect();
+	assert_eq!(8, parts.len());
+	assert!(
+		parts[1].ends_with('Z') && parts[1].contains('T'),
+		"bad timestamp in {msg}"
+	);
+	assert_eq!("test_svc", parts[3]);
+	assert_eq!(std::process::id().to_string(), parts[4]);
+	assert_eq!(
+		"handler{request_id=42}: log_sinks: Something broke user=bob",
+		parts[7]
+	);
+
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_log_sink_from_str() {
+	assert_eq!(LogSink::Stderr, "stderr".parse().unwrap());
+	assert_eq!(LogSink::journald(), "journald".parse().unwrap());
+	assert_eq!(
+		LogSink::Syslog("/tmp/sock".into()),
+		"syslog:/tmp/sock".parse().unwrap()
+	);
+	assert!("carrier-pigeon".parse::<LogSink>().is_err());
+
+	assert_eq!("syslog:/dev/log", LogSink::syslog().to_string());
+}
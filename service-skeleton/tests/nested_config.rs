@@ -0,0 +1,133 @@
+mod common;
+use common::vars;
+
+use secrecy::{ExposeSecret, SecretString};
+use service_skeleton::ServiceConfig;
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DatabaseConfig {
+	host: String,
+
+	#[config(default_value = "5432")]
+	port: u16,
+
+	#[config(sensitive)]
+	password: SecretString,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct HttpClientConfig {
+	#[config(default_value = "30")]
+	timeout: u32,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct ServiceWideConfig {
+	name: String,
+
+	#[config(nested)]
+	db: DatabaseConfig,
+
+	#[config(flatten)]
+	http: HttpClientConfig,
+}
+
+#[test]
+fn test_nested_and_flattened() {
+	std::env::set_var("NEST_DB_PASSWORD", "hunter2");
+
+	let cfg = ServiceWideConfig::from_env_vars(
+		"NEST",
+		vars(&[
+			("NEST_NAME", "Jaime"),
+			("NEST_DB_HOST", "db.example.com"),
+			("NEST_DB_PASSWORD", "hunter2"),
+			("NEST_TIMEOUT", "5"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!("Jaime", cfg.name);
+	assert_eq!("db.example.com", cfg.db.host);
+	assert_eq!(5432, cfg.db.port);
+	assert_eq!("hunter2", cfg.db.password.expose_secret());
+	assert_eq!(5, cfg.http.timeout);
+
+	// The nested struct's own sensitive fields still get purged
+	assert_eq!(
+		Err(std::env::VarError::NotPresent),
+		std::env::var("NEST_DB_PASSWORD")
+	);
+
+	assert_eq!(
+		r#"ServiceWideConfig { name: "Jaime", db: DatabaseConfig { host: "db.example.com", port: 5432, password: [REDACTED] }, http: HttpClientConfig { timeout: 5 } }"#,
+		format!("{:?}", cfg.redacted())
+	);
+}
+
+#[test]
+fn test_nested_error_names_full_var() {
+	let err =
+		ServiceWideConfig::from_env_vars("NEST2", vars(&[("NEST2_NAME", "Jaime")])).unwrap_err();
+
+	let missing: Vec<_> = err
+		.problems()
+		.into_iter()
+		.map(|e| {
+			if let service_skeleton::Error::ConfigValueRequired { var } = e {
+				var.as_str()
+			} else {
+				panic!("unexpected error {e:?}")
+			}
+		})
+		.collect();
+	assert_eq!(vec!["NEST2_DB_HOST", "NEST2_DB_PASSWORD"], missing);
+}
+
+#[test]
+fn test_sensitive_nested() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct SecretiveConfig {
+		#[config(nested, sensitive)]
+		upstream: HttpClientConfig,
+	}
+
+	std::env::set_var("SEKRIT_UPSTREAM_TIMEOUT", "42");
+
+	let cfg = SecretiveConfig::from_env_vars("SEKRIT", vars(&[("SEKRIT_UPSTREAM_TIMEOUT", "42")]))
+		.unwrap();
+
+	assert_eq!(42, cfg.upstream.timeout);
+	assert_eq!(
+		Err(std::env::VarError::NotPresent),
+		std::env::var("SEKRIT_UPSTREAM_TIMEOUT")
+	);
+	assert_eq!(
+		"SecretiveConfig { upstream: [REDACTED] }",
+		format!("{:?}", cfg.redacted())
+	);
+}
+
+#[test]
+fn test_nested_from_config_file() {
+	let file = std::env::temp_dir().join(format!(
+		"service-skeleton-{}-nested.toml",
+		std::process::id()
+	));
+	std::fs::write(
+		&file,
+		"name = \"Jaime\"\ntimeout = 10\n\n[db]\nhost = \"db.example.com\"\npassword = \"hunter2\"\n",
+	)
+	.unwrap();
+
+	let cfg = ServiceWideConfig::from_env_vars(
+		"NEST3",
+		vars(&[("NEST3_CONFIG_FILE", &file.to_string_lossy())]),
+	)
+	.unwrap();
+
+	assert_eq!("db.example.com", cfg.db.host);
+	assert_eq!(10, cfg.http.timeout);
+
+	std::fs::remove_file(&file).unwrap();
+}
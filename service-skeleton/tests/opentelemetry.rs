@@ -0,0 +1,103 @@
+#![cfg(feature = "opentelemetry")]
+
+// These tests need to run a whole service, which takes over the process, so each test re-runs
+// the test binary with the service-running half of the test selected via an env var, and plays
+// the part of the OTLP collector itself.
+
+mod common;
+
+use service_skeleton::service;
+
+use std::time::Duration;
+
+fn run_child(test_name: &str, env: &[(&str, &str)]) -> Vec<(String, Vec<u8>)> {
+	let collector = tiny_http::Server::http("127.0.0.1:0").unwrap();
+	let port = collector.server_addr().to_ip().unwrap().port();
+
+	let mut child = common::child_command(test_name, "OTEL_TEST_CHILD")
+		.env(
+			"OTEL_EXPORTER_OTLP_ENDPOINT",
+			format!("http://127.0.0.1:{port}"),
+		)
+		.env_remove("OTEL_SERVICE_NAME")
+		.env_remove("OTEL_TRACES_SAMPLER")
+		.env_remove("OTEL_SDK_DISABLED")
+		.envs(env.iter().copied())
+		.spawn()
+		.unwrap();
+
+	// The child waits for each export to be acknowledged, so keep collecting until it's done
+	let mut requests = Vec::new();
+	loop {
+		let exited = child.try_wait().unwrap();
+		match collector.recv_timeout(Duration::from_millis(100)).unwrap() {
+			Some(mut req) => {
+				let mut body = Vec::new();
+				req.as_reader().read_to_end(&mut body).unwrap();
+				requests.push((req.url().to_string(), body));
+				req.respond(tiny_http::Response::empty(200)).unwrap();
+			}
+			None => {
+				if let Some(status) = exited {
+					assert!(status.success());
+					return requests;
+				}
+			}
+		}
+	}
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+	haystack
+		.windows(needle.len())
+		.any(|w| w == needle.as_bytes())
+}
+
+fn traced_service(ratio: f64) {
+	service("Traced").trace_sample_ratio(ratio).run(|_cfg: ()| {
+		tracing::info_span!("handle_request", request_id = 42u32).in_scope(|| {
+			tracing::info!("handling request");
+		});
+		service_skeleton::logging::flush();
+		std::process::exit(0);
+	});
+}
+
+#[test]
+fn test_spans_exported() {
+	if std::env::var("OTEL_TEST_CHILD").is_ok() {
+		traced_service(1.0);
+	}
+
+	let requests = run_child("test_spans_exported", &[]);
+	assert_eq!(1, requests.len());
+
+	let (url, body) = &requests[0];
+	assert_eq!("/v1/traces", url);
+	assert!(contains(body, "handle_request"));
+	assert!(contains(body, "service.name"));
+	assert!(contains(body, "traced"));
+}
+
+#[test]
+fn test_sampled_out() {
+	if std::env::var("OTEL_TEST_CHILD").is_ok() {
+		traced_service(0.0);
+	}
+
+	assert!(run_child("test_sampled_out", &[]).is_empty());
+}
+
+#[test]
+fn test_exported_when_not_logged() {
+	if std::env::var("OTEL_TEST_CHILD").is_ok() {
+		traced_service(1.0);
+	}
+
+	let requests = run_child("test_exported_when_not_logged", &[("TRACED_LOG", "off")]);
+	assert_eq!(1, requests.len());
+
+	let (_, body) = &requests[0];
+	assert!(contains(body, "handle_request"));
+	assert!(contains(body, "handling request"));
+}
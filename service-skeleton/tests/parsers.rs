@@ -0,0 +1,183 @@
+mod common;
+use common::vars;
+
+use service_skeleton::{config::parsers, Error, ServiceConfig};
+
+use std::{
+	net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+	time::Duration,
+};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct DurationConfig {
+	#[config(default_value = "1m30s")]
+	timeout: Duration,
+
+	retry_after: Option<std::time::Duration>,
+
+	#[config(default_value = "1s,2s,4s")]
+	backoff: Vec<Duration>,
+}
+
+#[test]
+fn test_duration() {
+	assert_eq!(Ok(Duration::from_secs(30)), parsers::duration("30s"));
+	assert_eq!(Ok(Duration::from_secs(30)), parsers::duration("30"));
+	assert_eq!(Ok(Duration::from_millis(500)), parsers::duration("500ms"));
+	assert_eq!(Ok(Duration::from_secs(5400)), parsers::duration("1h30m"));
+	assert_eq!(
+		Ok(Duration::from_secs(5400)),
+		parsers::duration("1h 30 mins")
+	);
+	assert_eq!(Ok(Duration::from_secs(129_600)), parsers::duration("1.5d"));
+	assert_eq!(Ok(Duration::from_micros(250)), parsers::duration("250us"));
+	assert_eq!(
+		Ok(Duration::from_micros(250)),
+		parsers::duration("250\u{b5}s")
+	);
+	assert_eq!(
+		Ok(Duration::from_micros(250)),
+		parsers::duration("250\u{3bc}s")
+	);
+	assert_eq!(
+		Ok(Duration::from_secs(1_209_600)),
+		parsers::duration("2 weeks")
+	);
+
+	assert!(parsers::duration("").is_err());
+	assert!(parsers::duration("30 parsecs").is_err());
+	assert!(parsers::duration("1h30").is_err());
+	assert!(parsers::duration("s").is_err());
+	assert!(parsers::duration("1000000w").is_err());
+}
+
+#[test]
+fn test_byte_size() {
+	assert_eq!(Ok(512), parsers::byte_size("512"));
+	assert_eq!(Ok(512), parsers::byte_size("512B"));
+	assert_eq!(Ok(64_000), parsers::byte_size("64k"));
+	assert_eq!(Ok(10_000_000), parsers::byte_size("10MB"));
+	assert_eq!(Ok(10_485_760), parsers::byte_size("10MiB"));
+	assert_eq!(Ok(10_485_760), parsers::byte_size("10 mib"));
+	assert_eq!(Ok(1_500_000_000), parsers::byte_size("1.5GB"));
+	assert_eq!(Ok(1_099_511_627_776), parsers::byte_size("1TiB"));
+
+	assert!(parsers::byte_size("").is_err());
+	assert!(parsers::byte_size("lots").is_err());
+	assert!(parsers::byte_size("10 XB").is_err());
+	assert!(parsers::byte_size("10MB of RAM").is_err());
+	assert!(parsers::byte_size("100000PB").is_err());
+}
+
+#[test]
+fn test_boolean() {
+	for yes in ["true", "TRUE", "yes", "Y", "on", "1"] {
+		assert_eq!(Ok(true), parsers::boolean(yes), "{yes}");
+	}
+	for no in ["false", "No", "n", "OFF", "0"] {
+		assert_eq!(Ok(false), parsers::boolean(no), "{no}");
+	}
+	assert!(parsers::boolean("maybe").is_err());
+}
+
+#[test]
+fn test_socket_addr() {
+	assert_eq!(
+		Ok(SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1), 8080))),
+		parsers::socket_addr::<8080>("192.0.2.1")
+	);
+	assert_eq!(
+		Ok(SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1), 80))),
+		parsers::socket_addr::<8080>("192.0.2.1:80")
+	);
+	assert_eq!(
+		Ok(SocketAddr::from((Ipv6Addr::LOCALHOST, 8080))),
+		parsers::socket_addr::<8080>("::1")
+	);
+	assert_eq!(
+		Ok(SocketAddr::from((Ipv6Addr::LOCALHOST, 8080))),
+		parsers::socket_addr::<8080>("[::1]")
+	);
+	assert_eq!(
+		Ok(SocketAddr::from((Ipv6Addr::LOCALHOST, 80))),
+		parsers::socket_addr::<8080>("[::1]:80")
+	);
+
+	assert!(parsers::socket_addr::<8080>("localhost").is_err());
+	assert!(parsers::socket_addr::<8080>("192.0.2.1:http").is_err());
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn test_url() {
+	let url = parsers::url("https://example.com/api").unwrap();
+	assert_eq!(Some("example.com"), url.host_str());
+
+	assert!(parsers::url("localhost:8080").is_err());
+	assert!(parsers::url("not a URL").is_err());
+}
+
+#[test]
+fn test_derived_duration_fields() {
+	let cfg = DurationConfig::from_env_vars("DURATION", vars(&[])).unwrap();
+	assert_eq!(Duration::from_secs(90), cfg.timeout);
+	assert_eq!(None, cfg.retry_after);
+	assert_eq!(
+		vec![
+			Duration::from_secs(1),
+			Duration::from_secs(2),
+			Duration::from_secs(4)
+		],
+		cfg.backoff
+	);
+
+	let cfg = DurationConfig::from_env_vars(
+		"DURATION",
+		vars(&[
+			("DURATION_TIMEOUT", "250ms"),
+			("DURATION_RETRY_AFTER", "5m"),
+			("DURATION_BACKOFF", "10s"),
+		]),
+	)
+	.unwrap();
+	assert_eq!(Duration::from_millis(250), cfg.timeout);
+	assert_eq!(Some(Duration::from_secs(300)), cfg.retry_after);
+	assert_eq!(vec![Duration::from_secs(10)], cfg.backoff);
+
+	let err = DurationConfig::from_env_vars("DURATION", vars(&[("DURATION_TIMEOUT", "soon")]))
+		.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigValueParse { var, .. } if var == "DURATION_TIMEOUT"),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn test_other_durations_use_from_str() {
+	mod other {
+		/// A `Duration` that isn't `std::time::Duration`, and is given as a number of ticks.
+		#[derive(Clone, Debug, PartialEq)]
+		pub struct Duration(pub u64);
+
+		impl std::str::FromStr for Duration {
+			type Err = std::num::ParseIntError;
+
+			fn from_str(s: &str) -> Result<Self, Self::Err> {
+				s.parse().map(Self)
+			}
+		}
+	}
+
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct TickConfig {
+		#[config(default_value = "10")]
+		ticks: other::Duration,
+
+		#[config(default_value = "10")]
+		std_secs: std::time::Duration,
+	}
+
+	let cfg = TickConfig::from_env_vars("TICK", vars(&[])).unwrap();
+	assert_eq!(other::Duration(10), cfg.ticks);
+	assert_eq!(Duration::from_secs(10), cfg.std_secs);
+}
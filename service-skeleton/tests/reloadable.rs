@@ -0,0 +1,170 @@
+mod common;
+use common::temp_file;
+
+use service_skeleton::{testing::LogCapture, Reloadable, ServiceConfig};
+
+use std::{
+	path::PathBuf,
+	thread,
+	time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct ReloadConfig {
+	#[config(default_value = "World")]
+	name: String,
+
+	#[config(default_value = "8080")]
+	port: u16,
+
+	#[config(sensitive)]
+	password: Option<String>,
+}
+
+fn load(file: &PathBuf) -> Reloadable<ReloadConfig> {
+	Reloadable::<ReloadConfig>::from_env_vars(
+		"RELOAD",
+		[(
+			"RELOAD_CONFIG_FILE".to_string(),
+			file.to_string_lossy().to_string(),
+		)]
+		.into_iter(),
+	)
+	.unwrap()
+}
+
+#[test]
+fn test_reload() {
+	let file = temp_file("reload.toml", "name = \"Jaime\"\npassword = \"hunter2\"\n");
+	let cfg = load(&file);
+	assert_eq!("Jaime", cfg.get().name);
+
+	// Nothing has changed yet
+	assert!(!cfg.reload().unwrap());
+
+	std::fs::write(&file, "name = \"Bobbie\"\npassword = \"swordfish\"\n").unwrap();
+	let capture = LogCapture::new();
+	assert!(capture.run(|| cfg.reload()).unwrap());
+	assert_eq!("Bobbie", cfg.get().name);
+	assert_eq!(Some("swordfish"), cfg.get().password.as_deref());
+
+	let messages: Vec<_> = capture.events().into_iter().map(|e| e.message).collect();
+	assert!(
+		messages.contains(
+			&"Config field name changed: RELOAD_NAME changed from \"Jaime\" to \"Bobbie\""
+				.to_string()
+		),
+		"{messages:?}"
+	);
+	assert!(
+		messages.contains(&"Config field password changed (value redacted)".to_string()),
+		"{messages:?}"
+	);
+	assert!(
+		!messages
+			.iter()
+			.any(|m| m.contains("hunter2") || m.contains("swordfish")),
+		"{messages:?}"
+	);
+
+	// A broken config isn't used
+	std::fs::write(&file, "name = \"Jaime\"\nport = \"eleventy\"\n").unwrap();
+	assert!(cfg.reload().is_err());
+	assert_eq!("Bobbie", cfg.get().name);
+	assert_eq!(8080, cfg.get().port);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_env_secrets_survive_reload() {
+	let file = temp_file("env-secret.toml", "name = \"Jaime\"\n");
+	let path = file.to_string_lossy().to_string();
+	let cfg = Reloadable::<ReloadConfig>::from_env_vars(
+		"RELOAD",
+		common::vars(&[
+			("RELOAD_CONFIG_FILE", path.as_str()),
+			("RELOAD_PASSWORD", "hunter2"),
+		]),
+	)
+	.unwrap();
+
+	std::fs::write(&file, "name = \"Bobbie\"\n").unwrap();
+	let capture = LogCapture::new();
+	assert!(capture.run(|| cfg.reload()).unwrap());
+	assert_eq!("Bobbie", cfg.get().name);
+	assert_eq!(Some("hunter2"), cfg.get().password.as_deref());
+
+	// The password didn't change, so it doesn't get a mention
+	let messages: Vec<_> = capture.events().into_iter().map(|e| e.message).collect();
+	assert!(
+		!messages.iter().any(|m| m.contains("password")),
+		"{messages:?}"
+	);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_reload_leaves_env_alone() {
+	let file = temp_file("env-alone.toml", "name = \"Jaime\"\n");
+	let path = file.to_string_lossy().to_string();
+	let vars = [
+		("REENV_CONFIG_FILE", path.as_str()),
+		("REENV_PASSWORD", "hunter2"),
+	];
+	let cfg = Reloadable::<ReloadConfig>::from_env_vars("REENV", common::vars(&vars)).unwrap();
+
+	// Reading the config the first time takes secrets out of the environment, but a reload
+	// could be happening on any thread, so it has to leave the environment be
+	std::env::set_var("REENV_PASSWORD", "hunter2");
+	std::fs::write(&file, "name = \"Bobbie\"\n").unwrap();
+	assert!(cfg.reload().unwrap());
+	assert_eq!("Bobbie", cfg.get().name);
+	assert_eq!(Ok("hunter2".to_string()), std::env::var("REENV_PASSWORD"));
+
+	std::env::remove_var("REENV_PASSWORD");
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_clones_share_config() {
+	let file = temp_file("shared.toml", "name = \"Jaime\"\n");
+	let cfg = load(&file);
+	let other = cfg.clone();
+
+	std::fs::write(&file, "name = \"Bobbie\"\n").unwrap();
+	assert!(cfg.reload().unwrap());
+	assert_eq!("Bobbie", other.get().name);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_watch() {
+	let file = temp_file("watch.toml", "name = \"Jaime\"\n");
+	let cfg = load(&file);
+	cfg.watch(Duration::from_millis(10));
+
+	std::fs::write(&file, "name = \"Alexis Bobbie\"\n").unwrap();
+
+	let deadline = Instant::now() + Duration::from_secs(5);
+	while cfg.get().name == "Jaime" && Instant::now() < deadline {
+		thread::sleep(Duration::from_millis(10));
+	}
+	assert_eq!("Alexis Bobbie", cfg.get().name);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_debug_is_redacted() {
+	let file = temp_file("debug.toml", "password = \"hunter2\"\n");
+	let cfg = load(&file);
+
+	let debug = format!("{cfg:?}");
+	assert!(debug.starts_with("Reloadable(ReloadConfig {"), "{debug}");
+	assert!(!debug.contains("hunter2"), "{debug}");
+
+	std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,77 @@
+mod common;
+use common::vars;
+
+use service_skeleton::{testing::LogCapture, Error, ServiceConfig};
+use tracing::Level;
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct TypoConfig {
+	#[config(default_value = "postgres://localhost")]
+	database_url: String,
+
+	#[config(from_file)]
+	password: Option<String>,
+
+	labels: Option<std::collections::HashMap<String, String>>,
+}
+
+#[derive(Clone, Debug, ServiceConfig)]
+#[config(deny_unknown_vars)]
+struct StrictConfig {
+	name: String,
+}
+
+#[test]
+fn test_unknown_vars_warn() {
+	let capture = LogCapture::new();
+
+	let cfg = capture
+		.run(|| {
+			TypoConfig::from_env_vars(
+				"TYPO",
+				vars(&[
+					("TYPO_DATABSE_URL", "postgres://db"),
+					("TYPO_PASSWORD_FILE", "/dev/null"),
+					("TYPO_LABELS_TEAM", "core"),
+					("TYPO_LOG", "debug"),
+					("TYPO_METRICS_SERVER_PORT", "9090"),
+					("TYPO_WHATEVER_ELSE", "x"),
+					("OTHER_THING", "y"),
+				]),
+			)
+		})
+		.unwrap();
+
+	assert_eq!("postgres://localhost", cfg.database_url);
+
+	let warnings: Vec<_> = capture
+		.events()
+		.into_iter()
+		.filter(|e| e.level == Level::WARN)
+		.map(|e| e.message)
+		.collect();
+	assert_eq!(
+		vec![
+			"unknown config variable TYPO_DATABSE_URL; did you mean TYPO_DATABASE_URL?",
+			"unknown config variable TYPO_WHATEVER_ELSE",
+		],
+		warnings
+	);
+}
+
+#[test]
+fn test_unknown_vars_denied() {
+	let err = StrictConfig::from_env_vars(
+		"STRICT",
+		vars(&[("STRICT_NAME", "Jaime"), ("STRICT_NMAE", "Jamie")]),
+	)
+	.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigVarUnknown { var, suggestion: Some(s) } if var == "STRICT_NMAE" && s == "STRICT_NAME"),
+		"{err:?}"
+	);
+
+	// Unknown vars get reported alongside any other problems
+	let err = StrictConfig::from_env_vars("STRICT", vars(&[("STRICT_NAM", "Jaime")])).unwrap_err();
+	assert_eq!(2, err.problems().len(), "{err:?}");
+}
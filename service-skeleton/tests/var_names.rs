@@ -0,0 +1,184 @@
+mod common;
+use common::vars;
+
+use service_skeleton::{testing::LogCapture, Error, ServiceConfig};
+use tracing::Level;
+
+#[derive(Clone, Debug, ServiceConfig)]
+struct NamedConfig {
+	#[config(env = "DATABASE_URL")]
+	database_url: String,
+
+	#[config(env = "PORT", default_value = "8080")]
+	port: u16,
+
+	#[config(alias = "NAMED_OLD_NAME", alias = "ANCIENT_NAME")]
+	name: String,
+
+	#[config(alias = "NAMED_OLD_HOSTS")]
+	hosts: Vec<String>,
+}
+
+#[test]
+fn test_exact_names() {
+	let cfg = NamedConfig::from_env_vars(
+		"NAMED",
+		vars(&[
+			("DATABASE_URL", "postgres://db"),
+			("NAMED_DATABASE_URL", "postgres://ignored"),
+			("PORT", "1234"),
+			("NAMED_NAME", "Jaime"),
+			("NAMED_HOSTS", "a,b"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!("postgres://db", cfg.database_url);
+	assert_eq!(1234, cfg.port);
+	assert_eq!("Jaime", cfg.name);
+	assert_eq!(vec!["a", "b"], cfg.hosts);
+
+	let err = NamedConfig::from_env_vars(
+		"NAMED",
+		vars(&[
+			("DATABASE_URL", "postgres://db"),
+			("PORT", "http"),
+			("NAMED_NAME", "Jaime"),
+			("NAMED_HOSTS", "a,b"),
+		]),
+	)
+	.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigValueParse { var, .. } if var == "PORT"),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn test_aliases() {
+	let capture = LogCapture::new();
+
+	let cfg = capture
+		.run(|| {
+			NamedConfig::from_env_vars(
+				"NAMED",
+				vars(&[
+					("DATABASE_URL", "postgres://db"),
+					("ANCIENT_NAME", "Bobbie"),
+					("NAMED_OLD_HOSTS_0", "c"),
+				]),
+			)
+		})
+		.unwrap();
+
+	assert_eq!("Bobbie", cfg.name);
+	assert_eq!(vec!["c"], cfg.hosts);
+
+	let warnings: Vec<_> = capture
+		.events()
+		.into_iter()
+		.filter(|e| e.level == Level::WARN)
+		.map(|e| e.message)
+		.collect();
+	assert_eq!(
+		vec![
+			"ANCIENT_NAME is deprecated; please set NAMED_NAME instead",
+			"NAMED_OLD_HOSTS is deprecated; please set NAMED_HOSTS instead",
+		],
+		warnings
+	);
+
+	// The real name wins, and the earlier aliases beat the later ones
+	let cfg = NamedConfig::from_env_vars(
+		"NAMED",
+		vars(&[
+			("DATABASE_URL", "postgres://db"),
+			("NAMED_NAME", "Jaime"),
+			("NAMED_OLD_NAME", "Bobbie"),
+			("NAMED_HOSTS", "a"),
+			("NAMED_OLD_HOSTS", "b"),
+		]),
+	)
+	.unwrap();
+	assert_eq!("Jaime", cfg.name);
+	assert_eq!(vec!["a"], cfg.hosts);
+
+	let cfg = NamedConfig::from_env_vars(
+		"NAMED",
+		vars(&[
+			("DATABASE_URL", "postgres://db"),
+			("NAMED_OLD_NAME", "Bobbie"),
+			("ANCIENT_NAME", "Alex"),
+			("NAMED_HOSTS", "a"),
+		]),
+	)
+	.unwrap();
+	assert_eq!("Bobbie", cfg.name);
+
+	// If nothing's set, it's the real name that's missing
+	let err = NamedConfig::from_env_vars(
+		"NAMED",
+		vars(&[("DATABASE_URL", "postgres://db"), ("NAMED_HOSTS", "a")]),
+	)
+	.unwrap_err();
+	assert!(
+		matches!(&err, Error::ConfigValueRequired { var } if var == "NAMED_NAME"),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn test_sensitive_alias_is_purged() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct SecretConfig {
+		#[config(sensitive, alias = "OLD_SECRET_TOKEN")]
+		token: String,
+	}
+
+	std::env::set_var("OLD_SECRET_TOKEN", "hunter2");
+
+	let cfg =
+		SecretConfig::from_env_vars("SECRET", vars(&[("OLD_SECRET_TOKEN", "hunter2")])).unwrap();
+
+	assert_eq!("hunter2", cfg.token);
+	assert_eq!(
+		Err(std::env::VarError::NotPresent),
+		std::env::var("OLD_SECRET_TOKEN")
+	);
+}
+
+#[test]
+fn test_struct_prefix() {
+	#[derive(Clone, Debug, ServiceConfig)]
+	#[config(prefix = "LEGACY")]
+	struct LegacyConfig {
+		name: String,
+	}
+
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct ModernConfig {
+		#[config(nested)]
+		legacy: LegacyConfig,
+
+		#[config(nested, env = "UPSTREAM")]
+		upstream: UpstreamConfig,
+	}
+
+	#[derive(Clone, Debug, ServiceConfig)]
+	struct UpstreamConfig {
+		url: String,
+	}
+
+	let cfg = ModernConfig::from_env_vars(
+		"MODERN",
+		vars(&[
+			("LEGACY_NAME", "Jaime"),
+			("MODERN_LEGACY_NAME", "ignored"),
+			("UPSTREAM_URL", "https://example.com"),
+		]),
+	)
+	.unwrap();
+
+	assert_eq!("Jaime", cfg.legacy.name);
+	assert_eq!("https://example.com", cfg.upstream.url);
+}
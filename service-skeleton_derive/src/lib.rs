@@ -1,10 +1,10 @@
 #![allow(clippy::needless_continue)] // Darling's FromField implementation has a needless continue; I'll figure out how to fix it another time
 
-use darling::{ast, util::Flag, util::SpannedValue, FromDeriveInput, FromField};
-use heck::AsShoutySnekCase;
+use darling::{ast, util::Flag, util::SpannedValue, FromDeriveInput, FromField, FromMeta};
+use heck::{AsShoutySnekCase, AsSnekCase};
 use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned, ToTokens};
-use syn::{parse_macro_input, spanned::Spanned, ExprPath, Ident, Type};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{ext::IdentExt as _, parse_macro_input, spanned::Spanned, ExprPath, Ident, Type};
 
 #[proc_macro_derive(ServiceConfig, attributes(config))]
 pub fn derive_service_config(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -19,11 +19,17 @@ pub fn derive_service_config(input: proc_macro::TokenStream) -> proc_macro::Toke
 }
 
 #[derive(Debug, FromDeriveInput)]
-#[darling(supports(struct_named))]
+#[darling(attributes(config), supports(struct_named))]
 struct ServiceConfigReceiver {
 	ident: Ident,
 	generics: syn::Generics,
 	data: ast::Data<(), SpannedValue<ServiceConfigField>>,
+
+	cli: Flag,
+	debug: Flag,
+	deny_unknown_vars: Flag,
+	prefix: Option<String>,
+	validate: Option<ExprPath>,
 }
 
 impl ToTokens for ServiceConfigReceiver {
@@ -31,8 +37,16 @@ impl ToTokens for ServiceConfigReceiver {
 		let struct_name = &self.ident;
 		let (imp, ty, wher) = self.generics.split_for_impl();
 
-		let mut fields: Vec<TokenStream> = Vec::new();
+		let mut field_names: Vec<&Ident> = Vec::new();
+		let mut locals: Vec<Ident> = Vec::new();
+		let mut field_inits: Vec<TokenStream> = Vec::new();
 		let mut purges: Vec<TokenStream> = Vec::new();
+		let mut debug_fields: Vec<TokenStream> = Vec::new();
+		let mut var_docs: Vec<TokenStream> = Vec::new();
+		let mut info_fields: Vec<TokenStream> = Vec::new();
+		let mut field_vars: Vec<TokenStream> = Vec::new();
+		let mut needs_field_vars = false;
+		let mut unchecked_defaults = false;
 
 		#[allow(clippy::expect_used)] // Ensured by darling(supports(struct_named))
 		for f in self
@@ -42,107 +56,983 @@ impl ToTokens for ServiceConfigReceiver {
 			.expect("data to be a struct")
 			.fields
 		{
-			fields.push(f.field_init());
+			field_names.push(f.field_name());
+			locals.push(f.local_name());
+			field_inits.push(f.field_init());
 
 			purges.push(f.purge_sensitive());
+
+			debug_fields.push(f.debug_field());
+
+			var_docs.push(f.var_docs());
+
+			info_fields.push(f.config_info());
+
+			field_vars.push(f.field_vars());
+			needs_field_vars |= f.is_map() || f.is_nested();
+
+			unchecked_defaults |= f.has_unchecked_default_value();
 		}
 
+		let struct_name_str = struct_name.to_string();
+
+		// Map fields (including those in nested configs) need to know which vars belong to the
+		// other fields, so that they don't take them for entries of their own
+		let field_vars = needs_field_vars.then(|| {
+			quote! {
+				let field_vars: Vec<(&str, Vec<String>)> = vec![#(#field_vars),*];
+			}
+		});
+
+		let (prefix_arg, prefix) = if let Some(prefix) = &self.prefix {
+			(quote! { _prefix }, quote! { #prefix.to_string() })
+		} else {
+			(
+				quote! { prefix },
+				quote! { ::service_skeleton::heck::AsShoutySnekCase(prefix).to_string() },
+			)
+		};
+
+		let validate = self.validate.as_ref().map(|validate| {
+			quote! {
+				::service_skeleton::config::validate_with(&prefix, &cfg, #validate)?;
+			}
+		});
+
+		let deny_unknown_vars = self.deny_unknown_vars.is_present().then(|| {
+			quote! {
+				fn deny_unknown_vars() -> bool {
+					true
+				}
+			}
+		});
+
+		let cli_flags = self.cli.is_present().then(|| {
+			quote! {
+				fn cli_flags() -> bool {
+					true
+				}
+			}
+		});
+
 		tokens.extend(quote! {
 			impl #imp ServiceConfig for #struct_name #ty #wher {
-				fn from_env_vars(prefix: &str, vars: impl Iterator<Item = (String, String)>) -> Result<#struct_name, service_skeleton::Error> {
-					let prefix = ::service_skeleton::heck::AsShoutySnekCase(prefix).to_string();
-					let var_map: ::std::collections::HashMap<String, String> = vars.collect();
-					let mut key_map: ::std::collections::HashMap<::service_skeleton::config::Key, ::secrecy::SecretString> = ::std::collections::HashMap::new();
+				fn from_sources(#prefix_arg: &str, sources: &mut ::service_skeleton::config::Sources) -> Result<#struct_name, service_skeleton::Error> {
+					let prefix = #prefix;
+
+					// Every field gets read, even if an earlier one failed, so that all the
+					// problems with the config can be reported at once
+					let mut errors: Vec<::service_skeleton::Error> = Vec::new();
+
+					#field_vars
+
+					#(#field_inits)*
+
+					#[allow(irrefutable_let_patterns)] // Happens when the struct has no fields
+					let (#(Some(#locals),)*) = (#(#locals,)*) else {
+						return Err(::service_skeleton::Error::config_errors(errors));
+					};
 
 					let cfg = #struct_name {
-						#(#fields)*
+						#(#field_names: #locals),*
 					};
 
-					#(#purges)*
+					if sources.purges_env() {
+						#(#purges)*
+					}
+
+					#validate
 
 					Ok(cfg)
 				}
+
+				#deny_unknown_vars
+
+				#cli_flags
+
+				fn fields(#prefix_arg: &str) -> Vec<::service_skeleton::config::EnvVar> {
+					let prefix = #prefix;
+
+					let vars: Vec<Vec<::service_skeleton::config::EnvVar>> = vec![#(#var_docs),*];
+					vars.into_iter().flatten().collect()
+				}
+
+				#[allow(unused_variables)] // When there's nothing that can be shown
+				fn config_info(&self, path: &str, info: &mut ::service_skeleton::config::ConfigInfo) {
+					#[allow(unused_imports)] // All of them are needed to pick the best way to render each value
+					use ::service_skeleton::config::{LabelViaDebug as _, LabelViaDisplay as _, LabelViaPath as _};
+
+					#(#info_fields)*
+				}
+
+				fn fmt_redacted(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+					f.debug_struct(#struct_name_str)
+						#(#debug_fields)*
+						.finish()
+				}
 			}
 		});
+
+		// Defaults that couldn't be checked at compile time get checked by a test instead, so that
+		// a bad one is caught before it's needed in production; generic configs will have to be
+		// checked by hand, though, as there's no telling what to test them with
+		if unchecked_defaults && self.generics.params.is_empty() {
+			let test_name = format_ident!(
+				"__service_skeleton_check_defaults_{}",
+				AsSnekCase(struct_name.to_string()).to_string()
+			);
+			tokens.extend(quote! {
+				#[cfg(test)]
+				#[test]
+				#[allow(unnameable_test_items)] // The config might be declared within a test
+				fn #test_name() {
+					let problems = ::service_skeleton::config::default_value_problems::<#struct_name>(#struct_name_str);
+					assert!(
+						problems.is_empty(),
+						"invalid default_value for {}: {}",
+						#struct_name_str,
+						problems.join("; ")
+					);
+				}
+			});
+		}
+
+		if self.debug.is_present() {
+			tokens.extend(quote! {
+				impl #imp ::std::fmt::Debug for #struct_name #ty #wher {
+					fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+						<Self as ServiceConfig>::fmt_redacted(self, f)
+					}
+				}
+			});
+		}
 	}
 }
 
 #[derive(Debug, FromField)]
-#[darling(attributes(config))]
+#[darling(attributes(config), forward_attrs(doc))]
 struct ServiceConfigField {
 	ident: Option<Ident>,
 	ty: Type,
+	attrs: Vec<syn::Attribute>,
 
 	default_value: Option<SpannedValue<String>>,
+	default: Option<SpannedValue<DefaultExpr>>,
 	value_parser: Option<SpannedValue<ExprPath>>,
 	encrypted: Flag,
+	allow_plaintext: Flag,
 	sensitive: Flag,
 	key_file_field: Option<SpannedValue<String>>,
+	from_file: Flag,
+	hidden: Flag,
+	flatten: Flag,
+	nested: Flag,
+	separator: Option<SpannedValue<String>>,
+	env: Option<SpannedValue<String>>,
+	#[darling(multiple)]
+	alias: Vec<SpannedValue<String>>,
+	range: Option<SpannedValue<RangeSpec>>,
+	non_empty: Flag,
+	regex: Option<SpannedValue<String>>,
+	one_of: Option<SpannedValue<OneOf>>,
+	validate: Option<SpannedValue<ExprPath>>,
+}
+
+/// The bounds given in `#[config(range(min = ..., max = ...))]`.
+#[derive(Debug, FromMeta)]
+struct RangeSpec {
+	min: Option<syn::Expr>,
+	max: Option<syn::Expr>,
+}
+
+/// The default given in `#[config(default = ...)]`, or `None` for `#[config(default)]`, which
+/// means `Default::default()`.
+#[derive(Debug)]
+struct DefaultExpr(Option<syn::Expr>);
+
+impl FromMeta for DefaultExpr {
+	fn from_word() -> darling::Result<Self> {
+		Ok(Self(None))
+	}
+
+	// Not left to syn::Expr's FromMeta, which would parse a string literal as an expression,
+	// rather than taking it as the string it is
+	fn from_expr(expr: &syn::Expr) -> darling::Result<Self> {
+		Ok(Self(Some(expr.clone())))
+	}
+}
+
+/// The allowed values given in `#[config(one_of(...))]`.
+#[derive(Debug)]
+struct OneOf(Vec<syn::Lit>);
+
+impl FromMeta for OneOf {
+	fn from_list(items: &[ast::NestedMeta]) -> darling::Result<Self> {
+		items
+			.iter()
+			.map(|item| match item {
+				ast::NestedMeta::Lit(lit) => Ok(lit.clone()),
+				ast::NestedMeta::Meta(meta) => {
+					Err(darling::Error::custom("expected a literal value").with_span(meta))
+				}
+			})
+			.collect::<darling::Result<_>>()
+			.map(OneOf)
+	}
+}
+
+/// The kinds of collection that can be read from the environment without a `value_parser`
+/// having to do all the work.
+enum Collection<'a> {
+	/// Lists and sets, with the type of their elements
+	List(&'a Type),
+	/// Maps, with the types of their keys and values
+	Map(&'a Type, &'a Type),
 }
 
 impl ServiceConfigField {
+	/// Read the field's value into its `local_name`, which will be `None` if there was a problem
+	/// (and the problem will have been added to `errors`).
 	fn field_init(&self) -> TokenStream {
-		let field_name = self.field_name();
-		let fmt_str = Self::env_var_format_string(&field_name.to_string());
+		let local = self.local_name();
+		let ty = &self.ty;
+		let value = self.field_value();
+
+		quote_spanned! { self.ident.span()=>
+			let #local: Option<#ty> = ::service_skeleton::config::collect_error(&mut errors, || #value);
+		}
+	}
+
+	/// The expression that produces a `Result` containing the field's value.
+	fn field_value(&self) -> TokenStream {
+		if self.is_nested() {
+			return self.nested_field_init();
+		}
+
+		if let Some(error) = self.default_error() {
+			return error;
+		}
+
+		if let Some(collection) = self.collection() {
+			return self.collection_field_init(&collection);
+		}
+
+		if let Some(separator) = &self.separator {
+			return quote_spanned! { separator.span()=>
+				compile_error!("separator can only be used on list or set fields")
+			};
+		}
+
+		let var = if self.from_file.is_present() {
+			self.resolved_var_name(&quote! {
+				|s: &::service_skeleton::config::Sources, v: &str| s.get(v).is_some() || s.get(&format!("{v}_FILE")).is_some()
+			})
+		} else {
+			self.resolved_var_name(&quote! {
+				|s: &::service_skeleton::config::Sources, v: &str| s.get(v).is_some()
+			})
+		};
 		let value_parser = self.value_parser();
 		let default_value = self.default_value();
 		let fetch_value = self.fetch_value();
 
-		if self.is_optional() {
-			quote_spanned! { self.ident.span()=>
-				#field_name: ::service_skeleton::config::determine_optional_value(
-					&format!(#fmt_str, prefix),
+		let determine = if self.is_optional() || self.default.is_some() {
+			quote! { determine_optional_value }
+		} else {
+			quote! { determine_value }
+		};
+		let apply_default = self.apply_default();
+
+		let validations = self.validations(false);
+
+		quote_spanned! { self.ident.span()=>
+			{
+				let var = #var;
+				#fetch_value
+				let field_value = ::service_skeleton::config::#determine(
+					sources,
+					&var,
 					#value_parser,
-					#fetch_value,
+					value,
 					#default_value
-				)?,
+				)?;
+				#apply_default
+				#validations
+				Ok(field_value)
+			}
+		}
+	}
+
+	/// If the field has a `default`, the statement that uses it when the field's `field_value`
+	/// (which will have been read as an `Option`) isn't set.
+	fn apply_default(&self) -> Option<TokenStream> {
+		let ty = &self.ty;
+		let default = self.default.as_ref()?;
+
+		match (&default.as_ref().0, self.is_optional()) {
+			// The default for an Option is None, which is what it would be anyway
+			(None, true) => None,
+			(None, false) => Some(quote_spanned! { default.span()=>
+				let field_value: #ty = field_value.unwrap_or_default();
+			}),
+			(Some(expr), optional) => {
+				let or_else = if optional {
+					quote! { or_else }
+				} else {
+					quote! { unwrap_or_else }
+				};
+				Some(quote_spanned! { default.span()=>
+					// The default is only worked out if it's needed, however cheap it might be
+					#[allow(clippy::redundant_closure, clippy::unnecessary_lazy_evaluations)]
+					let field_value: #ty = field_value.#or_else(|| #expr);
+				})
+			}
+		}
+	}
+
+	/// A compile error, if the field's default doesn't make sense: because it has both a
+	/// `default` and a `default_value`, or because its `default_value` can be seen, right now,
+	/// not to parse.
+	fn default_error(&self) -> Option<TokenStream> {
+		if let (Some(_), Some(default)) = (&self.default_value, &self.default) {
+			return Some(quote_spanned! { default.span()=>
+				compile_error!("a field can have a default or a default_value, but not both")
+			});
+		}
+
+		let default_value = self.default_value.as_ref()?;
+		if let Some(Err(e)) = self.check_default_value() {
+			return Some(quote_spanned! { default_value.span()=> compile_error!(#e) });
+		}
+
+		None
+	}
+
+	/// Check that the `default_value` parses, if it's of a type that can be parsed here, at
+	/// compile time, rather than only when the default is needed.  `None` means that it can't be
+	/// checked (or there's nothing to check).
+	fn check_default_value(&self) -> Option<Result<(), String>> {
+		let default_value = self.default_value.as_ref()?.as_ref();
+		if self.value_parser.is_some() {
+			return None;
+		}
+
+		match self.collection() {
+			None => check_literal(self.value_type(), default_value),
+			Some(Collection::List(elem_ty)) => {
+				let separator = self.separator.as_ref().map_or(",", |s| s.as_ref().as_str());
+				default_value
+					.split(separator)
+					.map(str::trim)
+					.filter(|item| !item.is_empty())
+					.map(|item| check_literal(elem_ty, item))
+					.collect::<Option<Result<(), String>>>()
 			}
+			Some(Collection::Map(..)) => None,
+		}
+	}
+
+	/// Whether the field has a `default_value` which can only be checked by actually parsing it
+	/// at runtime.
+	fn has_unchecked_default_value(&self) -> bool {
+		self.default_value.is_some() && !self.is_nested() && self.check_default_value().is_none()
+	}
+
+	/// Initialise a list, set, or map field, whose elements are each parsed separately.
+	fn collection_field_init(&self, collection: &Collection<'_>) -> TokenStream {
+		let ty = &self.ty;
+
+		if let Some(span) = [
+			self.encrypted.is_present().then(|| self.encrypted.span()),
+			self.allow_plaintext
+				.is_present()
+				.then(|| self.allow_plaintext.span()),
+			self.key_file_field.as_ref().map(SpannedValue::span),
+			self.from_file.is_present().then(|| self.from_file.span()),
+		]
+		.into_iter()
+		.flatten()
+		.next()
+		{
+			return quote_spanned! { span=>
+				compile_error!("this attribute can't be used on a list, set, or map field")
+			};
+		}
+
+		match collection {
+			Collection::List(elem_ty) => {
+				let determine = if self.is_optional() || self.default.is_some() {
+					quote! { determine_optional_list }
+				} else {
+					quote! { determine_list }
+				};
+				let separator = self
+					.separator
+					.as_ref()
+					.map_or_else(|| ",".to_string(), |s| s.as_ref().clone());
+				let parser = self.element_parser(elem_ty);
+				let default_value = self.default_value();
+				let var = self.resolved_var_name(&quote! {
+					|s: &::service_skeleton::config::Sources, v: &str| s.get(v).is_some() || s.get(&format!("{v}_0")).is_some()
+				});
+
+				let apply_default = self.apply_default();
+				let field_value_ty = if self.default.is_some() && !self.is_optional() {
+					quote! { Option<#ty> }
+				} else {
+					quote! { #ty }
+				};
+
+				let validations = self.validations(true);
+
+				quote_spanned! { self.ident.span()=>
+					{
+						let var = #var;
+						let field_value: #field_value_ty = ::service_skeleton::config::#determine(
+							sources,
+							&var,
+							#separator,
+							#parser,
+							#default_value
+						)?;
+						#apply_default
+						#validations
+						Ok(field_value)
+					}
+				}
+			}
+			Collection::Map(key_ty, value_ty) => {
+				if let Some(span) = [
+					self.separator.as_ref().map(SpannedValue::span),
+					self.default_value.as_ref().map(SpannedValue::span),
+					self.range.as_ref().map(SpannedValue::span),
+					self.regex.as_ref().map(SpannedValue::span),
+					self.one_of.as_ref().map(SpannedValue::span),
+				]
+				.into_iter()
+				.flatten()
+				.next()
+				{
+					return quote_spanned! { span=>
+						compile_error!("this attribute can't be used on a map field")
+					};
+				}
+
+				let determine = if self.is_optional() || self.default.is_some() {
+					quote! { determine_optional_map }
+				} else {
+					quote! { determine_map }
+				};
+				let parser = self.element_parser(value_ty);
+				let siblings = self.siblings();
+				let var = self.resolved_var_name(&quote! {
+					|s: &::service_skeleton::config::Sources, v: &str| !s.with_prefix(&format!("{v}_"), &siblings).is_empty()
+				});
+
+				let apply_default = self.apply_default();
+				let field_value_ty = if self.default.is_some() && !self.is_optional() {
+					quote! { Option<#ty> }
+				} else {
+					quote! { #ty }
+				};
+
+				let validations = self.validations(true);
+
+				quote_spanned! { self.ident.span()=>
+					{
+						let siblings = #siblings;
+						let var = #var;
+						let field_value: #field_value_ty = ::service_skeleton::config::#determine(
+							sources,
+							&var,
+							&siblings,
+							|s: &str| s.parse::<#key_ty>(),
+							#parser,
+						)?;
+						#apply_default
+						#validations
+						Ok(field_value)
+					}
+				}
+			}
+		}
+	}
+
+	/// Initialise a field whose type is itself a `ServiceConfig`, which reads its values either
+	/// with the same prefix as this struct (if `flatten`), or with the field name added to the
+	/// prefix (if `nested`).
+	fn nested_field_init(&self) -> TokenStream {
+		let ty = &self.ty;
+
+		if self.flatten.is_present() && self.nested.is_present() {
+			return quote_spanned! { self.nested.span()=>
+				compile_error!("a field can be flattened or nested, but not both")
+			};
+		}
+		if let Some(span) = [
+			self.default_value.as_ref().map(SpannedValue::span),
+			self.default.as_ref().map(SpannedValue::span),
+			self.value_parser.as_ref().map(SpannedValue::span),
+			self.encrypted.is_present().then(|| self.encrypted.span()),
+			self.allow_plaintext
+				.is_present()
+				.then(|| self.allow_plaintext.span()),
+			self.key_file_field.as_ref().map(SpannedValue::span),
+			self.from_file.is_present().then(|| self.from_file.span()),
+			self.alias.first().map(SpannedValue::span),
+			self.range.as_ref().map(SpannedValue::span),
+			self.non_empty.is_present().then(|| self.non_empty.span()),
+			self.regex.as_ref().map(SpannedValue::span),
+			self.one_of.as_ref().map(SpannedValue::span),
+			self.validate.as_ref().map(SpannedValue::span),
+			self.env
+				.as_ref()
+				.filter(|_| self.flatten.is_present())
+				.map(SpannedValue::span),
+		]
+		.into_iter()
+		.flatten()
+		.next()
+		{
+			return quote_spanned! { span=>
+				compile_error!("this attribute can't be used on a flattened or nested field; put it on the fields of the nested struct instead")
+			};
+		}
+
+		let nested_prefix = if self.flatten.is_present() {
+			quote! { &prefix }
 		} else {
+			let var_name = self.var_name();
+			quote! { &#var_name }
+		};
+
+		let field = self.field_name().to_string();
+		let from_sources = quote_spanned! { self.ident.span()=>
+			sources.with_outer_vars(
+				::service_skeleton::config::sibling_vars(sources, &field_vars, #field),
+				|sources| <#ty as ::service_skeleton::config::Service>::from_sources(#nested_prefix, sources),
+			)
+		};
+
+		if self.is_sensitive() {
 			quote_spanned! { self.ident.span()=>
-				#field_name: ::service_skeleton::config::determine_value(
-					&format!(#fmt_str, prefix),
-					#value_parser,
-					#fetch_value,
-					#default_value
-				)?,
+				sources.sensitively(|sources| #from_sources)
 			}
+		} else {
+			from_sources
 		}
 	}
 
+	/// Generate the statements that fetch the raw value for the field from `sources`, into a
+	/// `value` variable.
 	fn fetch_value(&self) -> TokenStream {
-		let field_var_fmt_str = Self::env_var_format_string(&self.field_name().to_string());
+		let fetch_raw = if self.from_file.is_present() {
+			quote_spanned! { self.from_file.span()=>
+				let raw = ::service_skeleton::config::fetch_from_file(sources, &var)?;
+			}
+		} else {
+			quote_spanned! { self.ident.span()=>
+				let raw = sources.get(&var).map(|v| ::service_skeleton::secrecy::zeroize::Zeroizing::new(v.to_string()));
+			}
+		};
 
 		if self.encrypted.is_present() {
+			if !(self.protects_value() || self.allow_plaintext.is_present()) {
+				return quote_spanned! { self.encrypted.span()=>
+					compile_error!("encrypted field's type doesn't wipe the value when it's dropped; use a secret type (like SecretString), or add allow_plaintext if that's OK")
+				};
+			}
+
 			if let Some(ref key_file_field) = self.key_file_field {
 				let key_var_fmt_str = Self::env_var_format_string(key_file_field);
 
 				quote_spanned! { self.ident.span()=>
-					::service_skeleton::config::fetch_encrypted_field(&var_map, &mut key_map, &format!(#field_var_fmt_str, prefix), &::service_skeleton::config::Key::File(format!(#key_var_fmt_str, prefix)))?.as_deref()
+					#fetch_raw
+					let decrypted = ::service_skeleton::config::fetch_encrypted_field(sources, &var, raw, &::service_skeleton::config::Key::File(format!(#key_var_fmt_str, prefix)))?;
+					let value = decrypted.as_ref().map(::service_skeleton::secrecy::ExposeSecret::expose_secret);
 				}
 			} else {
 				quote_spanned! { self.encrypted.span()=>
 					compile_error!("field is encrypted but no key_file was specified to decrypt");
 				}
 			}
+		} else if self.allow_plaintext.is_present() {
+			quote_spanned! { self.allow_plaintext.span()=>
+				compile_error!("allow_plaintext can only be used on encrypted fields")
+			}
 		} else {
 			quote_spanned! { self.ident.span()=>
-				var_map.get(&format!(#field_var_fmt_str, prefix)).map(::std::string::String::as_str)
+				#fetch_raw
+				let value = raw.as_deref().map(::std::string::String::as_str);
 			}
 		}
 	}
 
+	/// Whether the field's type (as far as we can tell) keeps its contents secret, and wipes
+	/// them when it's dropped, so that a decrypted value doesn't hang around in memory.
+	fn protects_value(&self) -> bool {
+		let Type::Path(tp) = self.value_type() else {
+			return false;
+		};
+		tp.qself.is_none()
+			&& tp.path.segments.last().is_some_and(|last| {
+				["SecretString", "SecretBox", "SecretSlice", "Zeroizing"]
+					.iter()
+					.any(|name| last.ident == name)
+			})
+	}
+
+	/// The checks for all of the field's validation attributes, run against the `field_value`
+	/// (if there is one).  The `range`, `regex`, and `one_of` checks are run against each element
+	/// of the value, if `per_element` is set, and the value as a whole otherwise.
+	fn validations(&self, per_element: bool) -> TokenStream {
+		let mut checks = quote! {};
+
+		if self.non_empty.is_present() {
+			checks.extend(quote_spanned! { self.non_empty.span()=>
+				::service_skeleton::config::validate_non_empty(&var, v.is_empty())?;
+			});
+		}
+
+		if per_element {
+			let element_checks = self.element_validations(&quote! { item });
+			if !element_checks.is_empty() {
+				checks.extend(quote_spanned! { self.ident.span()=>
+					for item in v {
+						#element_checks
+					}
+				});
+			}
+		} else {
+			checks.extend(self.element_validations(&quote! { v }));
+		}
+
+		if let Some(validate) = &self.validate {
+			let validator = validate.as_ref();
+			checks.extend(quote_spanned! { validate.span()=>
+				::service_skeleton::config::validate_with(&var, v, #validator)?;
+			});
+		}
+
+		if checks.is_empty() {
+			checks
+		} else if self.is_optional() {
+			quote_spanned! { self.ident.span()=>
+				if let Some(v) = &field_value {
+					#checks
+				}
+			}
+		} else {
+			quote_spanned! { self.ident.span()=>
+				{
+					let v = &field_value;
+					#checks
+				}
+			}
+		}
+	}
+
+	/// The checks for the `range`, `regex`, and `one_of` validation attributes, where `v` is a
+	/// reference to the value being checked.
+	fn element_validations(&self, v: &TokenStream) -> TokenStream {
+		let mut checks = quote! {};
+
+		if let Some(range) = &self.range {
+			let min = range
+				.min
+				.as_ref()
+				.map_or_else(|| quote! { None }, |min| quote! { Some(#min) });
+			let max = range
+				.max
+				.as_ref()
+				.map_or_else(|| quote! { None }, |max| quote! { Some(#max) });
+			checks.extend(quote_spanned! { range.span()=>
+				::service_skeleton::config::validate_range(&var, #v, #min, #max)?;
+			});
+		}
+
+		if let Some(regex) = &self.regex {
+			let pattern = regex.as_ref();
+			if let Err(e) = regex_syntax::Parser::new().parse(pattern) {
+				let msg = format!("invalid regex: {e}");
+				checks.extend(quote_spanned! { regex.span()=> compile_error!(#msg); });
+			} else {
+				checks.extend(quote_spanned! { regex.span()=>
+					::service_skeleton::config::validate_regex(&var, ::std::convert::AsRef::<str>::as_ref(#v), #pattern)?;
+				});
+			}
+		}
+
+		if let Some(one_of) = &self.one_of {
+			let allowed = &one_of.as_ref().0;
+			checks.extend(quote_spanned! { one_of.span()=>
+				::service_skeleton::config::validate_one_of(&var, #v, &[#(#allowed),*])?;
+			});
+		}
+
+		checks
+	}
+
 	fn purge_sensitive(&self) -> TokenStream {
+		// Nested configs take care of their own purging
+		if self.is_nested() {
+			return quote! {};
+		}
+
+		let mut purge = quote! {};
+		let var_names = std::iter::once(self.var_name()).chain(self.alias.iter().map(|alias| {
+			let alias = alias.as_ref();
+			quote! { ::std::string::String::from(#alias) }
+		}));
+		for var_name in var_names {
+			if self.is_map() {
+				let siblings = self.siblings();
+				purge.extend(quote_spanned! { self.ident.span()=>
+					::service_skeleton::config::purge_map(sources, &#var_name, &#siblings);
+				});
+			} else if self.collection().is_some() {
+				purge.extend(quote_spanned! { self.ident.span()=>
+					::service_skeleton::config::purge_list(&#var_name);
+				});
+			} else {
+				let purge_file = self.from_file.is_present().then(|| {
+					quote_spanned! { self.ident.span()=>
+						::tracing::debug!("Removing sensitive env var {}_FILE", var);
+						::std::env::remove_var(format!("{var}_FILE"));
+					}
+				});
+				purge.extend(quote_spanned! { self.ident.span()=>
+					{
+						let var = #var_name;
+						::tracing::debug!("Removing sensitive env var {}", var);
+						::std::env::remove_var(&var);
+						#purge_file
+					}
+				});
+			}
+		}
+
 		if self.is_sensitive() {
-			let fmt_str = Self::env_var_format_string(&self.field_name().to_string());
+			purge
+		} else {
+			// The field might still be sensitive, if this config is nested in a sensitive field
 			quote_spanned! { self.ident.span()=>
-				::tracing::debug!("Removing sensitive env var {}", format!(#fmt_str, prefix));
-				::std::env::remove_var(&format!(#fmt_str, prefix));
+				if sources.all_sensitive() {
+					#purge
+				}
+			}
+		}
+	}
+
+	/// The descriptions of the env var(s) the field is read from.
+	fn var_docs(&self) -> TokenStream {
+		let sensitive = self.is_sensitive();
+		let field = self.field_name().to_string();
+
+		if self.is_nested() {
+			let ty = &self.ty;
+			let nested_prefix = if self.flatten.is_present() {
+				quote! { &prefix }
+			} else {
+				let var_name = self.var_name();
+				quote! { &#var_name }
+			};
+
+			return quote_spanned! { self.ident.span()=>
+				{
+					let mut vars = <#ty as ::service_skeleton::config::Service>::fields(#nested_prefix);
+					for var in &mut vars {
+						var.field = var.field.as_ref().map(|f| format!("{}.{f}", #field));
+						var.sensitive |= #sensitive;
+					}
+					vars
+				}
+			};
+		}
+
+		let var_name = self.var_name();
+		let kind = match self.collection() {
+			None => quote! { Value },
+			Some(Collection::List(_)) => quote! { List },
+			Some(Collection::Map(..)) => quote! { Map },
+		};
+		let type_name = type_name(self.value_type());
+		let default_value = self.default_value();
+		let default_expr = self.default.as_ref().map_or_else(
+			|| quote! { None },
+			|default| {
+				let text = default
+					.as_ref()
+					.0
+					.as_ref()
+					.map_or_else(|| "Default::default()".to_string(), source_text);
+				quote! { Some(#text) }
+			},
+		);
+		let description = self.description();
+		let optional = self.is_optional();
+		let encrypted = self.encrypted.is_present();
+		let from_file = self.from_file.is_present();
+		let is_bool = is_bool(self.value_type());
+		let key_file = self.key_file_field.as_ref().map_or_else(
+			|| quote! { None },
+			|key_file_field| {
+				let key_var_fmt_str = Self::env_var_format_string(key_file_field);
+				quote! { Some(format!(#key_var_fmt_str, prefix)) }
+			},
+		);
+
+		quote_spanned! { self.ident.span()=>
+			{
+				let mut var = ::service_skeleton::config::EnvVar::new(
+					#var_name,
+					::service_skeleton::config::EnvVarKind::#kind,
+					#type_name,
+				);
+				var.field = Some(#field.to_string());
+				var.default = #default_value;
+				var.default_expr = #default_expr;
+				var.description = #description;
+				var.optional = #optional;
+				var.sensitive = #sensitive;
+				var.encrypted = #encrypted;
+				var.from_file = #from_file;
+				var.key_file = #key_file;
+				var.is_bool = #is_bool;
+				vec![var]
+			}
+		}
+	}
+
+	/// The field's doc comment, with the leading space that `///` leaves on each line removed.
+	fn description(&self) -> String {
+		self.attrs
+			.iter()
+			.filter(|attr| attr.path().is_ident("doc"))
+			.filter_map(|attr| {
+				if let syn::Meta::NameValue(syn::MetaNameValue {
+					value:
+						syn::Expr::Lit(syn::ExprLit {
+							lit: syn::Lit::Str(s),
+							..
+						}),
+					..
+				}) = &attr.meta
+				{
+					Some(s.value())
+				} else {
+					None
+				}
+			})
+			.map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+			.collect::<Vec<_>>()
+			.join("\n")
+			.trim()
+			.to_string()
+	}
+
+	/// Add the field's value to the `info` for the `<service>_config_info` metric, unless it's a
+	/// secret.
+	fn config_info(&self) -> TokenStream {
+		if self.is_sensitive() || self.encrypted.is_present() {
+			return quote! {};
+		}
+
+		let field_name = self.field_name();
+		// A raw identifier's `r#` isn't allowed in a label name, and isn't part of the name anyway
+		let field_name_str = field_name.unraw().to_string();
+		let hidden = self.hidden.is_present();
+
+		// Label names can only have letters, digits, and underscores in them, so the names of
+		// nested fields are joined with a double underscore, and a name that could be mistaken
+		// for a join (or for the hash label) has to be ruled out
+		if !self.flatten.is_present() {
+			if field_name_str.contains("__") || field_name_str.ends_with('_') {
+				return quote_spanned! { self.ident.span()=>
+					compile_error!("a field's name can't contain a double underscore or end with an underscore, because that's how the names of nested fields are joined in the config_info metric's labels; make it sensitive if it mustn't be shown, or rename it");
+				};
+			}
+			if field_name_str == "config_hash" {
+				return quote_spanned! { self.ident.span()=>
+					compile_error!("config_hash is the config_info metric's label for the hash of the config, so it can't be the name of a field as well; make it sensitive if it mustn't be shown, or rename it");
+				};
+			}
+		}
+
+		if self.is_nested() {
+			let ty = &self.ty;
+			let nested_path = if self.flatten.is_present() {
+				quote! { path }
+			} else {
+				quote! { &::service_skeleton::config::ConfigInfo::join(path, #field_name_str) }
+			};
+			quote_spanned! { self.ident.span()=>
+				info.hidden_if(#hidden, |info| {
+					<#ty as ::service_skeleton::config::Service>::config_info(&self.#field_name, #nested_path, info);
+				});
 			}
 		} else {
-			quote! {}
+			let value = self.info_value();
+			quote_spanned! { self.ident.span()=>
+				info.add(path, #field_name_str, #value, #hidden);
+			}
 		}
 	}
 
+	/// The field's value as a label value: an `Option` field is unwrapped (and is `None` if
+	/// unset), and a collection has each of its elements rendered.
+	fn info_value(&self) -> TokenStream {
+		let field_name = self.field_name();
+		let render = |v: TokenStream| {
+			quote! { (&&&::service_skeleton::config::LabelValue(#v)).label_value() }
+		};
+
+		let value = match self.collection() {
+			Some(Collection::List(_)) => {
+				let elem = render(quote! { e });
+				let sorted = self.is_set();
+				quote! { ::service_skeleton::config::ConfigInfo::list(v.iter().map(|e| #elem), #sorted) }
+			}
+			Some(Collection::Map(..)) => {
+				let key = render(quote! { k });
+				let elem = render(quote! { e });
+				quote! { ::service_skeleton::config::ConfigInfo::map(v.iter().map(|(k, e)| (#key, #elem))) }
+			}
+			None => render(quote! { v }),
+		};
+
+		if self.is_optional() {
+			quote! { self.#field_name.as_ref().map(|v| #value) }
+		} else {
+			quote! { Some({ let v = &self.#field_name; #value }) }
+		}
+	}
+
+	fn debug_field(&self) -> TokenStream {
+		let field_name = self.field_name();
+		let field_name_str = field_name.to_string();
+
+		if self.is_sensitive() || self.encrypted.is_present() {
+			quote_spanned! { self.ident.span()=>
+				.field(#field_name_str, &format_args!("[REDACTED]"))
+			}
+		} else if self.is_nested() {
+			quote_spanned! { self.ident.span()=>
+				.field(#field_name_str, &::service_skeleton::config::Service::redacted(&self.#field_name))
+			}
+		} else {
+			quote_spanned! { self.ident.span()=>
+				.field(#field_name_str, &self.#field_name)
+			}
+		}
+	}
+
+	/// The local variable that the field's value is read into, named so that it can't clash with
+	/// any of the other variables used in the generated code.
+	fn local_name(&self) -> Ident {
+		format_ident!("field_{}", self.field_name())
+	}
+
 	fn field_name(&self) -> &Ident {
 		#[allow(clippy::expect_used)]
 		self.ident
@@ -150,6 +1040,86 @@ impl ServiceConfigField {
 			.expect("named field does not have a field")
 	}
 
+	/// The name of the env var that the field's value is read from, which is `<PREFIX>_<FIELD>`,
+	/// unless the field has been given an exact `env` name.
+	fn var_name(&self) -> TokenStream {
+		if let Some(env) = &self.env {
+			let name = env.as_ref();
+			quote_spanned! { env.span()=> ::std::string::String::from(#name) }
+		} else {
+			let fmt_str = Self::env_var_format_string(&self.field_name().to_string());
+			quote_spanned! { self.ident.span()=> format!(#fmt_str, prefix) }
+		}
+	}
+
+	/// The `(field, vars)` pair for the field, naming the vars (or, for maps and nested configs,
+	/// the prefixes of the vars) that it reads.
+	fn field_vars(&self) -> TokenStream {
+		let field = self.field_name().to_string();
+
+		let vars = if self.is_nested() {
+			let ty = &self.ty;
+			let nested_prefix = if self.flatten.is_present() {
+				quote! { &prefix }
+			} else {
+				let var_name = self.var_name();
+				quote! { &#var_name }
+			};
+			quote_spanned! { self.ident.span()=>
+				<#ty as ::service_skeleton::config::Service>::fields(#nested_prefix)
+					.into_iter()
+					.map(|var| var.name)
+					.collect::<Vec<String>>()
+			}
+		} else {
+			let var_name = self.var_name();
+			let aliases = self.alias.iter().map(AsRef::as_ref);
+			quote_spanned! { self.ident.span()=>
+				vec![#var_name, #(::std::string::String::from(#aliases)),*]
+			}
+		};
+
+		quote! { (#field, #vars) }
+	}
+
+	/// The vars that belong to the other fields of the config, which a map field has to leave
+	/// alone.
+	fn siblings(&self) -> TokenStream {
+		let field = self.field_name().to_string();
+		quote_spanned! { self.ident.span()=>
+			::service_skeleton::config::sibling_vars(sources, &field_vars, #field)
+		}
+	}
+
+	/// Whether the field is a set, whose elements come in no particular order.
+	fn is_set(&self) -> bool {
+		let Type::Path(tp) = self.value_type() else {
+			return false;
+		};
+		tp.path
+			.segments
+			.last()
+			.is_some_and(|s| s.ident == "HashSet" || s.ident == "BTreeSet")
+	}
+
+	fn is_map(&self) -> bool {
+		!self.is_nested() && matches!(self.collection(), Some(Collection::Map(..)))
+	}
+
+	/// The name of the env var to actually read the field's value from, which might be one of the
+	/// field's `alias`es.  `is_set` is a closure that says whether a var has a value for the field.
+	fn resolved_var_name(&self, is_set: &TokenStream) -> TokenStream {
+		let var_name = self.var_name();
+		if self.alias.is_empty() {
+			return var_name;
+		}
+
+		let aliases = self.alias.iter().map(AsRef::as_ref);
+		quote_spanned! { self.ident.span()=>
+			::service_skeleton::config::resolve_var(sources, #var_name, &[#(#aliases),*], #is_set)
+		}
+	}
+
 	fn env_var_format_string(field_name: &str) -> String {
 		format!(
 			"{{}}_{shouty_field_name}",
@@ -161,6 +1131,10 @@ impl ServiceConfigField {
 		self.sensitive.is_present()
 	}
 
+	fn is_nested(&self) -> bool {
+		self.flatten.is_present() || self.nested.is_present()
+	}
+
 	fn is_optional(&self) -> bool {
 		#[allow(clippy::wildcard_enum_match_arm)] // Yes, that's rather the point here
 		match &self.ty {
@@ -209,15 +1183,66 @@ impl ServiceConfigField {
 		}
 	}
 
+	/// Figure out if the field is a collection that we know how to fill, and if so, what the
+	/// types of its elements are.
+	fn collection(&self) -> Option<Collection<'_>> {
+		let Type::Path(tp) = self.value_type() else {
+			return None;
+		};
+		if tp.qself.is_some() {
+			return None;
+		}
+
+		let last = tp.path.segments.last()?;
+		let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+			return None;
+		};
+		let mut types = args.args.iter().filter_map(|arg| {
+			if let syn::GenericArgument::Type(t) = arg {
+				Some(t)
+			} else {
+				None
+			}
+		});
+
+		match last.ident.to_string().as_str() {
+			"Vec" | "HashSet" | "BTreeSet" => types.next().map(Collection::List),
+			"HashMap" | "BTreeMap" => Some(Collection::Map(types.next()?, types.next()?)),
+			_ => None,
+		}
+	}
+
+	/// The parser for each element of a collection, which is the field's `value_parser` if it has
+	/// one.
+	fn element_parser(&self, elem_ty: &Type) -> TokenStream {
+		if let Some(value_parser) = &self.value_parser {
+			let parser = value_parser.as_ref();
+			quote_spanned! { value_parser.span()=> #parser }
+		} else {
+			self.default_parser(elem_ty)
+		}
+	}
+
 	fn value_parser(&self) -> TokenStream {
 		if let Some(value_parser) = &self.value_parser {
 			// The as_ref() turns SpannedValue<T> into something that impls ToTokens, somehow
 			let parser = value_parser.as_ref();
 			quote_spanned! { value_parser.span()=> #parser }
 		} else {
-			let value_type = self.value_type();
+			self.default_parser(self.value_type())
+		}
+	}
+
+	/// How to parse a value of type `ty` when there's no `value_parser`: with `FromStr`, unless
+	/// it's a type that we know doesn't implement it, but which we can parse anyway.
+	fn default_parser(&self, ty: &Type) -> TokenStream {
+		if is_duration(ty) {
+			quote_spanned! { self.ident.span()=>
+				::service_skeleton::config::parsers::duration
+			}
+		} else {
 			quote_spanned! { self.ident.span()=>
-				|s: &str| s.parse::<#value_type>()
+				|s: &str| s.parse::<#ty>()
 			}
 		}
 	}
@@ -233,6 +1258,126 @@ impl ServiceConfigField {
 	}
 }
 
+/// Whether `ty` is (as far as we can tell) `std::time::Duration`, which is written as `Duration`,
+/// `std::time::Duration`, or `core::time::Duration`.  Other `Duration`s (like `chrono`'s) are
+/// left to `FromStr`.
+fn is_duration(ty: &Type) -> bool {
+	is_one_of(
+		ty,
+		&[
+			&["Duration"],
+			&["std", "time", "Duration"],
+			&["core", "time", "Duration"],
+		],
+	)
+}
+
+/// Whether `ty` is a `bool`, which is written as `bool`, or `std::primitive::bool` (or `core`'s).
+fn is_bool(ty: &Type) -> bool {
+	is_one_of(
+		ty,
+		&[
+			&["bool"],
+			&["std", "primitive", "bool"],
+			&["core", "primitive", "bool"],
+		],
+	)
+}
+
+/// Whether `ty` is a plain path (with or without a leading `::`) that's one of the `paths`.
+fn is_one_of(ty: &Type, paths: &[&[&str]]) -> bool {
+	let Type::Path(tp) = ty else {
+		return false;
+	};
+	if tp.qself.is_some() || tp.path.segments.iter().any(|s| !s.arguments.is_empty()) {
+		return false;
+	}
+
+	let idents: Vec<String> = tp
+		.path
+		.segments
+		.iter()
+		.map(|s| s.ident.to_string())
+		.collect();
+	let path: Vec<&str> = idents.iter().map(String::as_str).collect();
+	paths.contains(&path.as_slice())
+}
+
+/// The name of a type, as it would be written in the source (more or less), rather than with
+/// spaces between every token.
+fn type_name(ty: &Type) -> String {
+	source_text(ty)
+}
+
+/// Some code, as it would be written in the source (more or less), rather than with spaces
+/// between every token.
+fn source_text(code: &impl ToTokens) -> String {
+	[
+		(" < ", "<"),
+		("< ", "<"),
+		(" >", ">"),
+		(" :: ", "::"),
+		(":: ", "::"),
+		(" ,", ","),
+		("& ", "&"),
+		("[ ", "["),
+		(" ]", "]"),
+		(" ;", ";"),
+		(" (", "("),
+		("( ", "("),
+		(" )", ")"),
+		(" .", "."),
+		(". ", "."),
+	]
+	.into_iter()
+	.fold(code.to_token_stream().to_string(), |text, (from, to)| {
+		text.replace(from, to)
+	})
+}
+
+/// Check that `value` can be parsed as a `ty`, if `ty` is one of the types that we know how to
+/// parse.  `None` means that it isn't.
+fn check_literal(ty: &Type, value: &str) -> Option<Result<(), String>> {
+	fn parses<T: std::str::FromStr>(value: &str) -> Result<(), String>
+	where
+		T::Err: std::fmt::Display,
+	{
+		value.parse::<T>().map(|_| ()).map_err(|e| e.to_string())
+	}
+
+	let Type::Path(tp) = ty else {
+		return None;
+	};
+	if tp.qself.is_some() {
+		return None;
+	}
+	let name = tp.path.get_ident()?.to_string();
+
+	let result = match name.as_str() {
+		"bool" => parses::<bool>(value),
+		"char" => parses::<char>(value),
+		"u8" => parses::<u8>(value),
+		"u16" => parses::<u16>(value),
+		"u32" => parses::<u32>(value),
+		"u64" => parses::<u64>(value),
+		"u128" => parses::<u128>(value),
+		"i8" => parses::<i8>(value),
+		"i16" => parses::<i16>(value),
+		"i32" => parses::<i32>(value),
+		"i64" => parses::<i64>(value),
+		"i128" => parses::<i128>(value),
+		"f32" => parses::<f32>(value),
+		"f64" => parses::<f64>(value),
+		// Anything goes
+		"String" | "PathBuf" | "OsString" => Ok(()),
+		// usize and isize end up here too, as their width depends on the target, not the host
+		// we're running on, so they're left to the generated defaults test
+		_ => return None,
+	};
+
+	Some(result.map_err(|e| format!("default_value {value:?} is not a valid {name}: {e}")))
+}
+
 // Only used in integration tests
 #[cfg(test)]
 use trybuild as _;
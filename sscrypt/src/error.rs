@@ -12,4 +12,7 @@ pub enum Error {
 
 	#[error("invalid string: {0}")]
 	InvalidString(#[from] std::string::FromUtf8Error),
+
+	#[error("invalid string: {0}")]
+	InvalidSecretString(#[from] std::str::Utf8Error),
 }
@@ -1,5 +1,5 @@
 use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
-use secrecy::{ExposeSecret, SecretString};
+use secrecy::{zeroize::Zeroizing, ExposeSecret, SecretString};
 use strong_box::{SharedStrongBox, SharedStrongBoxKey, StrongBox};
 
 mod error;
@@ -43,20 +43,45 @@ pub fn encrypt(plaintext: &str, ctx: &str, key: &str) -> Result<String, Error> {
 	Ok(BASE64_URL_SAFE_NO_PAD.encode(strong_box.encrypt(plaintext.as_bytes(), ctx.as_bytes())?))
 }
 
+/// Decrypt `ciphertext`, which was encrypted for the field `ctx`, with the private `key`.
+///
+/// The plaintext is handed back as an ordinary `String`, which won't be wiped when it's dropped;
+/// use [`decrypt_secret`] to keep it secret.
 pub fn decrypt(ciphertext: &str, ctx: &str, key: &SecretString) -> Result<String, Error> {
+	let mut plaintext = open(ciphertext, ctx, key)?;
+	Ok(String::from_utf8(std::mem::take(&mut *plaintext))?)
+}
+
+/// Decrypt `ciphertext`, which was encrypted for the field `ctx`, with the private `key`.
+///
+/// The plaintext never lives anywhere that isn't wiped when it's dropped, until it's handed
+/// back as a `SecretString`.
+pub fn decrypt_secret(
+	ciphertext: &str,
+	ctx: &str,
+	key: &SecretString,
+) -> Result<SecretString, Error> {
+	let plaintext = open(ciphertext, ctx, key)?;
+	// Copied, rather than converted in place, because String::from_utf8 would hand the bytes
+	// back inside the error (where they wouldn't get wiped) if they aren't UTF-8
+	Ok(std::str::from_utf8(&plaintext)?.to_string().into())
+}
+
+fn open(ciphertext: &str, ctx: &str, key: &SecretString) -> Result<Zeroizing<Vec<u8>>, Error> {
 	let ciphertext = BASE64_URL_SAFE_NO_PAD.decode(ciphertext)?;
 	let keydata = key
 		.expose_secret()
 		.strip_prefix(PRIVATE_KEY_PREFIX)
 		.ok_or_else(|| Error::invalid_key("incorrect prefix"))?;
+	let keydata = Zeroizing::new(BASE64_URL_SAFE_NO_PAD.decode(keydata)?);
 	#[allow(clippy::shadow_unrelated)] // Au contraire, monsieur Clippy...
-	let key = SharedStrongBoxKey::try_from(&BASE64_URL_SAFE_NO_PAD.decode(keydata)?)?;
+	let key = SharedStrongBoxKey::try_from(&*keydata)?;
 
 	let strong_box = SharedStrongBox::new(key);
 
-	Ok(String::from_utf8(
+	Ok(Zeroizing::new(
 		strong_box.decrypt(ciphertext, ctx.as_bytes())?,
-	)?)
+	))
 }
 
 // These are purely "safety" types, to make sure that the tuple of key strings we pass back from